@@ -0,0 +1,68 @@
+//! Throughput of keystream generation.
+//! Run with `cargo bench`, input size can be changed with `BENCH_BYTES` (64 MiB by default).
+
+use std::env;
+use std::io::{self, Result};
+use std::time::Instant;
+
+use lfsr::{long_primitive_polynomial, primitive_polynomial, xor_stream, GaloisLfsr, Keystream, Lfsr, LongLfsr};
+
+const TAPS: [u8; 4] = [0, 1, 3, 4];
+const SEED: u64 = 0xABCDABCD_ABCDABCD;
+
+fn bench<F: FnMut() -> Result<()>>(name: &str, bytes: usize, mut run: F) {
+    let start = Instant::now();
+    run().unwrap();
+    let secs = start.elapsed().as_secs_f64();
+    println!("{name:<32} {secs:>8.2} s {:>10.1} MiB/s", bytes as f64 / secs / (1 << 20) as f64);
+}
+
+fn main() {
+    let bytes = env::var("BENCH_BYTES").ok()
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(1usize << 26);
+    let data = vec![0x5Au8; bytes];
+    let lfsr = Lfsr::new(&TAPS, SEED, 64).unwrap();
+
+    println!("{} MiB of input", bytes >> 20);
+
+    bench("bit by bit", bytes, || {
+        let mut reg = lfsr.clone();
+        let mut acc = 0u8;
+        for _ in 0..bytes {
+            acc ^= (0..8).fold(0u8, |b, _| (b << 1) | reg.next_bit());
+        }
+        std::hint::black_box(acc);
+        Ok(())
+    });
+    bench("galois bit by bit", bytes, || {
+        let mut reg = GaloisLfsr::from(&lfsr);
+        let mut acc = 0u8;
+        for _ in 0..bytes {
+            acc ^= reg.next_byte();
+        }
+        std::hint::black_box(acc);
+        Ok(())
+    });
+    for count in [8, 32, 64] {
+        bench(&format!("{count} bits per call"), bytes, || {
+            let mut reg = lfsr.clone();
+            let mut acc = 0u64;
+            for _ in 0..bytes * 8 / count as usize {
+                acc ^= reg.next_bits(count);
+            }
+            std::hint::black_box(acc);
+            Ok(())
+        });
+    }
+    bench("xor_stream", bytes, || xor_stream(&mut data.as_slice(), &mut io::sink(), &mut lfsr.clone()));
+
+    // Table polynomials have taps next to the top, so new bits depend on each other
+    let table = Lfsr::with_polynomial(&primitive_polynomial(64).unwrap(), SEED).unwrap();
+    bench("primitive 64 xor_stream", bytes, || xor_stream(&mut data.as_slice(), &mut io::sink(), &mut table.clone()));
+    for length in [127, 521] {
+        let poly = long_primitive_polynomial(length).unwrap();
+        let mut long = LongLfsr::with_polynomial(&poly, &[SEED]).unwrap();
+        bench(&format!("primitive {length} xor_stream"), bytes, || xor_stream(&mut data.as_slice(), &mut io::sink(), &mut long));
+    }
+}
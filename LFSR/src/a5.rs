@@ -0,0 +1,89 @@
+use crate::{Keystream, Lfsr};
+
+/// Register lengths, taps and clocking bits of A5/1.
+/// The spec numbers bits from the input end, here bit 0 is the output end,
+/// so spec bit `i` of a register of length `n` is bit `n - 1 - i`.
+const LENGTHS: [u8; 3] = [19, 22, 23];
+const TAPS: [&[u8]; 3] = [&[0, 1, 2, 5], &[0, 1], &[0, 1, 2, 15]];
+const CLOCK_BITS: [u8; 3] = [10, 11, 12];
+
+/// A5/1 stream cipher from GSM: three registers of 19, 22 and 23 bits, clocked
+/// irregularly by majority of their clocking bits, output is the xor of their outputs.
+///
+/// `Keystream` produces the bits continuously after the key setup, `frame_keystream`
+/// gives the two 114 bit bursts of a GSM frame.
+#[derive(Clone, Debug)]
+pub struct A51 {
+    regs: [Lfsr; 3],
+}
+
+impl A51 {
+    /// Loads 64 bit `key` and 22 bit `frame` number and runs 100 mixing clocks
+    pub fn new(key: &[u8; 8], frame: u32) -> Self {
+        let register = |i: usize| Lfsr { reg: 0, ..Lfsr::new(TAPS[i], 1, LENGTHS[i]).unwrap() };
+        let mut a51 = A51 { regs: [register(0), register(1), register(2)] };
+
+        // Key and frame bits are xored in least significant first, all registers clocked
+        let key_bits = (0..64).map(|i| key[i / 8] >> (i % 8) & 1);
+        let frame_bits = (0..22).map(|i| (frame >> i & 1) as u8);
+        for bit in key_bits.chain(frame_bits) {
+            a51.regs.iter_mut().for_each(|r| { r.clock_with_input(bit); });
+        }
+        (0..100).for_each(|_| a51.clock());
+        a51
+    }
+
+    /// Keystream for frame number `frame`: first burst for A to B direction, second for B to A,
+    /// 114 bits each packed most significant first
+    pub fn frame_keystream(key: &[u8; 8], frame: u32) -> ([u8; 15], [u8; 15]) {
+        let mut a51 = A51::new(key, frame);
+        let mut bursts = ([0u8; 15], [0u8; 15]);
+        for burst in [&mut bursts.0, &mut bursts.1] {
+            for i in 0..114 {
+                burst[i / 8] |= a51.next_bit() << (7 - i % 8);
+            }
+        }
+        bursts
+    }
+
+    /// Clocks registers whose clocking bit agrees with the majority
+    fn clock(&mut self) {
+        let bits: [u8; 3] = std::array::from_fn(|i| self.regs[i].bit(CLOCK_BITS[i]));
+        let majority = (bits[0] & bits[1]) | (bits[0] & bits[2]) | (bits[1] & bits[2]);
+        for (reg, bit) in self.regs.iter_mut().zip(bits) {
+            if bit == majority { reg.next_bit(); }
+        }
+    }
+}
+
+impl Keystream for A51 {
+    fn next_bit(&mut self) -> u8 {
+        self.clock();
+        self.regs.iter().fold(0, |acc, r| acc ^ r.bit(0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: [u8; 8] = [0x12, 0x23, 0x45, 0x67, 0x89, 0xAB, 0xCD, 0xEF];
+
+    #[test]
+    fn reference_vector_test() {
+        // Test vector from the reference implementation by Briceno, Goldberg and Wagner
+        let (a_to_b, b_to_a) = A51::frame_keystream(&KEY, 0x134);
+        assert_eq!(a_to_b, [0x53, 0x4E, 0xAA, 0x58, 0x2F, 0xE8, 0x15, 0x1A, 0xB6, 0xE1, 0x85, 0x5A, 0x72, 0x8C, 0x00]);
+        assert_eq!(b_to_a, [0x24, 0xFD, 0x35, 0xA3, 0x5D, 0x5F, 0xB6, 0x52, 0x6D, 0x32, 0xF9, 0x06, 0xDF, 0x1A, 0xC0]);
+    }
+
+    #[test]
+    fn keystream_test() {
+        // Continuous keystream starts with the first burst
+        let mut a51 = A51::new(&KEY, 0x134);
+        let mut bytes = [0u8; 14];
+        a51.fill_bytes(&mut bytes);
+        assert_eq!(bytes, A51::frame_keystream(&KEY, 0x134).0[..14]);
+        assert_ne!(A51::frame_keystream(&KEY, 0x135), A51::frame_keystream(&KEY, 0x134));
+    }
+}
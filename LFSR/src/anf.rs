@@ -0,0 +1,128 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::nfsr::Feedback;
+use crate::Error;
+
+/// Boolean function in algebraic normal form, a xor of monomials like `x0 + x3*x5 + x7`.
+///
+/// Variables `x0` to `x63` are bits of a register state, each monomial is the
+/// mask of its variables and the empty monomial is the constant 1.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Anf {
+    /// Distinct monomials ordered by their variables, constant last
+    monomials: Vec<u64>,
+}
+
+/// Indices of variables in monomial `m`
+fn variables(m: u64) -> Vec<u32> {
+    (0..64).filter(|i| m >> i & 1 == 1).collect()
+}
+
+impl Anf {
+    /// Function from monomial masks, repeated monomials cancel out
+    pub fn from_monomials(monomials: &[u64]) -> Self {
+        let mut sorted = monomials.to_vec();
+        sorted.sort_unstable();
+        let mut unique: Vec<u64> = Vec::with_capacity(sorted.len());
+        for m in sorted {
+            if unique.last() == Some(&m) { unique.pop(); } else { unique.push(m); }
+        }
+        unique.sort_by_key(|&m| (m == 0, variables(m)));
+        Anf { monomials: unique }
+    }
+
+    pub fn monomials(&self) -> &[u64] {
+        &self.monomials
+    }
+
+    /// Value of the function with variable `xi` set to bit `i` of `x`
+    pub fn eval(&self, x: u64) -> u8 {
+        (self.monomials.iter().filter(|&&m| x & m == m).count() & 1) as u8
+    }
+
+    /// Largest number of variables in a monomial
+    pub fn degree(&self) -> u32 {
+        self.monomials.iter().map(|m| m.count_ones()).max().unwrap_or(0)
+    }
+
+    /// Mask of variables the function depends on
+    pub fn variables(&self) -> u64 {
+        self.monomials.iter().fold(0, |acc, m| acc | m)
+    }
+}
+
+impl Feedback for Anf {
+    fn feedback(&self, state: &[u64]) -> u8 {
+        self.eval(state[0])
+    }
+}
+
+impl fmt::Display for Anf {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.monomials.is_empty() { return f.write_str("0"); }
+        for (i, &m) in self.monomials.iter().enumerate() {
+            if i > 0 { f.write_str(" + ")?; }
+            if m == 0 { f.write_str("1")?; continue; }
+            let vars: Vec<String> = variables(m).iter().map(|v| format!("x{}", v)).collect();
+            f.write_str(&vars.join("*"))?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Anf {
+    type Err = Error;
+
+    /// Parses notation like `x0 + x3*x5 + x7 + 1`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.trim() == "0" { return Ok(Anf { monomials: Vec::new() }); }
+        let mut monomials = Vec::new();
+        for term in s.split('+').map(str::trim) {
+            let mut m = 0u64;
+            for factor in term.split('*').map(str::trim) {
+                if factor == "1" { continue; }
+                let var: u32 = factor.strip_prefix('x').or_else(|| factor.strip_prefix('X'))
+                    .and_then(|v| v.parse().ok())
+                    .ok_or_else(|| Error::InvalidFunction(format!("can't parse '{}' in term '{}'", factor, term)))?;
+                if var >= 64 {
+                    return Err(Error::InvalidFunction(format!("variable x{} is above x63", var)));
+                }
+                m |= 1 << var;
+            }
+            monomials.push(m);
+        }
+        Ok(Anf::from_monomials(&monomials))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_test() {
+        let anf: Anf = "x0 + x3*x5 + x7".parse().unwrap();
+        assert_eq!(anf.monomials(), [1, 0b101000, 0x80]);
+        assert_eq!(anf.to_string(), "x0 + x3*x5 + x7");
+        assert_eq!(anf.degree(), 2);
+        assert_eq!(anf.variables(), 0b1010_1001);
+
+        // Repeated monomials cancel, variables in a monomial are a set
+        let anf: Anf = "1 + x7 + x0*x7 + x2 * x2 + x7 + X0".parse().unwrap();
+        assert_eq!(anf.to_string(), "x0 + x0*x7 + x2 + 1");
+        assert_eq!("x1 + x1".parse::<Anf>().unwrap().to_string(), "0");
+        assert_eq!("0".parse::<Anf>().unwrap().degree(), 0);
+
+        assert!(matches!("x0 + y1".parse::<Anf>(), Err(Error::InvalidFunction(_))));
+        assert!(matches!("x0 + ".parse::<Anf>(), Err(Error::InvalidFunction(_))));
+        assert!(matches!("x64".parse::<Anf>(), Err(Error::InvalidFunction(_))));
+    }
+
+    #[test]
+    fn eval_test() {
+        let anf: Anf = "x0 + x1*x2 + 1".parse().unwrap();
+        let values: Vec<u8> = (0..8).map(|x| anf.eval(x)).collect();
+        assert_eq!(values, [1, 0, 1, 0, 1, 0, 0, 1]);
+    }
+}
@@ -0,0 +1,205 @@
+use crate::berlekamp_massey::pack;
+use crate::randomness::erfc;
+use crate::{berlekamp_massey, bits_from_bytes, Error, Keystream, Lfsr, SIGNIFICANCE};
+
+/// Recovers the register that encrypted `ciphertext` from a known prefix of its plaintext.
+///
+/// The returned register is at the start of the keystream, so xoring the whole `ciphertext`
+/// with it decrypts it. A register of length `n` is determined by `2n` known bits, and another
+/// byte is required to confirm it, so a 64 bit register needs 17 bytes of known plaintext.
+pub fn recover_lfsr(ciphertext: &[u8], known: &[u8]) -> Result<Lfsr, Error> {
+    let keystream: Vec<u8> = ciphertext.iter().zip(known).map(|(c, p)| c ^ p).collect();
+    let bits = bits_from_bytes(&keystream);
+    let result = berlekamp_massey(&bits);
+    // Random bits also give a register of about half their length, which the extra byte rules out
+    let needed = 2 * result.length + 8;
+    if needed > bits.len() {
+        return Err(Error::KeystreamTooShort(needed));
+    }
+    result.to_lfsr(&bits)
+}
+
+/// Longest register the correlation attack searches exhaustively
+const MAX_ATTACK_LENGTH: u8 = 32;
+
+/// Best candidate of a correlation attack on one register
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Correlation {
+    pub seed: u64,
+    /// Number of keystream bits equal to the output of the register with `seed`
+    pub agreements: usize,
+    pub bits: usize,
+    /// Number of seeds tried, the best of which is this one
+    pub seeds: u64,
+}
+
+impl Correlation {
+    /// Fraction of keystream bits agreeing with the register, 0.5 means no correlation
+    pub fn ratio(&self) -> f64 {
+        self.agreements as f64 / self.bits as f64
+    }
+
+    /// Correlation coefficient of the register output and the keystream, `2 * ratio - 1`
+    pub fn correlation(&self) -> f64 {
+        2.0 * self.ratio() - 1.0
+    }
+
+    /// Probability that the best of `seeds` uncorrelated registers moves as far from half
+    /// of the bits. Agreements of one register are close to normal with deviation
+    /// `sqrt(bits) / 2`, and the union bound over the seeds gives the result.
+    pub fn p_value(&self) -> f64 {
+        let distance = (self.agreements as f64 - self.bits as f64 / 2.0).abs();
+        (self.seeds as f64 * erfc(distance * (2.0 / self.bits as f64).sqrt())).min(1.0)
+    }
+}
+
+/// Output of a register with every possible seed packed into words, first bit lowest
+fn candidates(taps: &[u8], length: u8, words: usize) -> Result<impl Iterator<Item = (u64, Vec<u64>)> + '_, Error> {
+    if length > MAX_ATTACK_LENGTH { return Err(Error::InvalidLength(length as u32)); }
+    Lfsr::new(taps, 1, length)?;
+    Ok((1..1u64 << length).map(move |seed| {
+        let mut lfsr = Lfsr::new(taps, seed, length).unwrap();
+        (seed, (0..words).map(|_| lfsr.next_bits(64)).collect())
+    }))
+}
+
+/// Siegenthaler's correlation attack on one register of a combination generator.
+///
+/// Tries every seed of the register with `taps` and `length` and returns the one whose output
+/// is the furthest from agreeing with half of the `keystream` bits. This finds registers the
+/// combining function is correlated with, one register at a time instead of all seeds at once.
+///
+/// Fewer than `length` bits can't tell the seeds apart. When the best seed has a p-value of
+/// `SIGNIFICANCE` or more, chance explains it as well and the result is `SeedNotFound`:
+/// the register isn't correlated with the keystream, or the bias is too small for its length.
+pub fn correlation_attack(keystream: &[u8], taps: &[u8], length: u8) -> Result<Correlation, Error> {
+    if keystream.len() < length as usize {
+        return Err(Error::KeystreamTooShort(length as usize));
+    }
+    let words = pack(keystream.iter().copied());
+    let last_mask = match keystream.len() % 64 {
+        0 => u64::MAX,
+        rest => u64::MAX >> (64 - rest),
+    };
+    let half = keystream.len() / 2;
+    let mut best: Option<Correlation> = None;
+    for (seed, mut output) in candidates(taps, length, words.len())? {
+        if let Some(last) = output.last_mut() { *last &= last_mask; }
+        let differ: usize = words.iter().zip(&output).map(|(w, o)| (w ^ o).count_ones() as usize).sum();
+        let agreements = keystream.len() - differ;
+        if agreements.abs_diff(half) > best.map_or(0, |b| b.agreements.abs_diff(half)) {
+            best = Some(Correlation { seed, agreements, bits: keystream.len(), seeds: (1 << length) - 1 });
+        }
+    }
+    best.filter(|b| b.p_value() < SIGNIFICANCE).ok_or(Error::SeedNotFound)
+}
+
+/// Recovers seeds of a `Geffe` generator from its output `keystream` and the
+/// `(taps, length)` of its registers. The first and the third registers agree with
+/// the output 3/4 of the time and are found by `correlation_attack`, then the selector
+/// is the register choosing between them wherever they differ.
+pub fn attack_geffe(keystream: &[u8], registers: [(&[u8], u8); 3]) -> Result<[u64; 3], Error> {
+    let x1 = correlation_attack(keystream, registers[0].0, registers[0].1)?.seed;
+    let x3 = correlation_attack(keystream, registers[2].0, registers[2].1)?.seed;
+    let bits = |(taps, length): (&[u8], u8), seed| {
+        let mut lfsr = Lfsr::new(taps, seed, length).unwrap();
+        keystream.iter().map(|_| lfsr.next_bit()).collect::<Vec<u8>>()
+    };
+    let (out1, out3) = (bits(registers[0], x1), bits(registers[2], x3));
+    // Where the registers differ, output tells the selector bit
+    let selector: Vec<(usize, u8)> = (0..keystream.len())
+        .filter(|&i| out1[i] != out3[i])
+        .map(|i| (i, (keystream[i] == out1[i]) as u8))
+        .collect();
+    let (taps, length) = registers[1];
+    let words = keystream.len().div_ceil(64);
+    let x2 = candidates(taps, length, words)?
+        .find(|(_, out)| selector.iter().all(|&(i, bit)| (out[i / 64] >> (i % 64) & 1) as u8 == bit))
+        .map(|(seed, _)| seed)
+        .ok_or(Error::SeedNotFound)?;
+    Ok([x1, x2, x3])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{primitive_polynomial, xor_stream, Geffe};
+    use std::fs::{self, File};
+    use std::io::{BufReader, BufWriter};
+
+    #[test]
+    fn recover_file_test() {
+        let dir = std::env::temp_dir().join(format!("lfsr-recover-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let (plain_path, cipher_path) = (dir.join("plain"), dir.join("cipher"));
+
+        let plain: Vec<u8> = b"Known header of the file: "
+            .iter().copied()
+            .chain((0..10000u32).map(|i| (i * 7 + i / 13) as u8))
+            .collect();
+        fs::write(&plain_path, &plain).unwrap();
+        let mut lfsr = Lfsr::new(&[0, 1, 3, 4], 0xDEAD_BEEF_0BAD_F00D, 64).unwrap();
+        let mut reader = BufReader::new(File::open(&plain_path).unwrap());
+        let mut writer = BufWriter::new(File::create(&cipher_path).unwrap());
+        xor_stream(&mut reader, &mut writer, &mut lfsr).unwrap();
+        drop(writer);
+
+        let ciphertext = fs::read(&cipher_path).unwrap();
+        let mut recovered = recover_lfsr(&ciphertext, &plain[..24]).unwrap();
+        assert_eq!(recovered.taps(), [0, 1, 3, 4]);
+        assert_eq!(recovered.state(), 0xDEAD_BEEF_0BAD_F00D);
+
+        let mut decrypted = Vec::new();
+        xor_stream(&mut ciphertext.as_slice(), &mut decrypted, &mut recovered).unwrap();
+        assert_eq!(decrypted, plain);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn geffe_attack_test() {
+        let taps: Vec<Vec<u8>> = [11, 12, 13].iter().map(|&n| primitive_polynomial(n).unwrap().taps().unwrap()).collect();
+        let registers = [(taps[0].as_slice(), 11), (taps[1].as_slice(), 12), (taps[2].as_slice(), 13)];
+        let seeds = [0x5A3, 0xBEE, 0x1234];
+        let lfsr = |i: usize| Lfsr::new(registers[i].0, seeds[i], registers[i].1).unwrap();
+        let mut geffe = Geffe::new(lfsr(0), lfsr(1), lfsr(2));
+        let keystream: Vec<u8> = (0..300).map(|_| geffe.next_bit()).collect();
+
+        let x1 = correlation_attack(&keystream, registers[0].0, 11).unwrap();
+        assert_eq!(x1.seed, 0x5A3);
+        assert!(x1.ratio() > 0.7 && x1.p_value() < 1e-9);
+        // Selector alone isn't correlated with the output
+        assert_eq!(correlation_attack(&keystream, registers[1].0, 12).unwrap_err(), Error::SeedNotFound);
+
+        assert_eq!(attack_geffe(&keystream, registers).unwrap(), seeds);
+    }
+
+    #[test]
+    fn too_short_test() {
+        let mut lfsr = Lfsr::new(&[0, 1, 3, 4], 0xDEAD_BEEF_0BAD_F00D, 64).unwrap();
+        let mut ciphertext = [0u8; 32];
+        lfsr.apply_keystream(&mut ciphertext);
+        assert!(matches!(recover_lfsr(&ciphertext, &[0; 8]), Err(Error::KeystreamTooShort(_))));
+        assert_eq!(recover_lfsr(&ciphertext, &[0; 16]).unwrap_err(), Error::KeystreamTooShort(136));
+        assert_eq!(recover_lfsr(&ciphertext, &[0; 17]).unwrap().state(), 0xDEAD_BEEF_0BAD_F00D);
+    }
+
+    #[test]
+    fn correlation_too_short_test() {
+        assert_eq!(correlation_attack(&[], &[0, 1], 4).unwrap_err(), Error::KeystreamTooShort(4));
+        // Seed 01 of the 2 bit register outputs 1, 0, 1, but one of 3 seeds agrees that well by chance
+        assert_eq!(correlation_attack(&[1, 0, 1], &[0, 1], 2).unwrap_err(), Error::SeedNotFound);
+        let mut lfsr = Lfsr::new(&[0, 1], 0b01, 2).unwrap();
+        let keystream: Vec<u8> = (0..30).map(|_| lfsr.next_bit()).collect();
+        assert_eq!(correlation_attack(&keystream, &[0, 1], 2).unwrap().seed, 0b01);
+    }
+
+    #[test]
+    fn p_value_test() {
+        // 60 of 100 bits is 2 deviations away, erfc(2 / sqrt(2)) = 0.0455
+        let correlation = Correlation { seed: 1, agreements: 60, bits: 100, seeds: 1 };
+        assert!((correlation.p_value() - 0.0455).abs() < 1e-4);
+        assert!((correlation.correlation() - 0.2).abs() < 1e-12);
+        assert!((Correlation { seeds: 10, ..correlation }.p_value() - 0.455).abs() < 1e-3);
+        assert_eq!(Correlation { seeds: 100, ..correlation }.p_value(), 1.0);
+    }
+}
@@ -0,0 +1,208 @@
+use crate::{Error, Lfsr, Polynomial};
+
+/// Shortest register generating a sequence, found by `berlekamp_massey`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LinearComplexity {
+    /// Length of the shortest register, the linear complexity of the sequence
+    pub length: usize,
+    /// Coefficients `c_0..=c_length` of the connection polynomial, `c_0` is always 1,
+    /// so that `s[j] = c_1*s[j-1] + ... + c_length*s[j-length]`
+    pub connection: Vec<u8>,
+    /// Linear complexity of each prefix of the sequence
+    pub profile: Vec<usize>,
+}
+
+impl LinearComplexity {
+    /// Taps of the register, same as used by `Lfsr`
+    pub fn taps(&self) -> Vec<usize> {
+        (1..=self.length).rev()
+            .filter(|&i| self.connection[i] == 1)
+            .map(|i| self.length - i)
+            .collect()
+    }
+
+    /// Connection polynomial, if its degree equals the register length,
+    /// otherwise the register has no tap 0
+    pub fn polynomial(&self) -> Result<Polynomial, Error> {
+        if self.connection[self.length] == 0 {
+            return Err(Error::MissingTapZero);
+        }
+        let terms: Vec<u32> = (1..=self.length as u32).filter(|&i| self.connection[i as usize] == 1).collect();
+        Polynomial::from_terms(&terms)
+    }
+
+    /// Register that generates `bits` (the sequence this was computed from).
+    ///
+    /// `Lfsr` needs a non-zero seed and at least two taps, so complexity 0 (all zero bits)
+    /// gives `ZeroSeed` and a single tap, like complexity 1 of all one bits, `TooFewTaps`.
+    pub fn to_lfsr(&self, bits: &[u8]) -> Result<Lfsr, Error> {
+        if self.length > 64 { return Err(Error::InvalidLength(self.length as u32)); }
+        if bits.len() < self.length { return Err(Error::KeystreamTooShort(self.length)); }
+        if self.length == 0 { return Err(Error::ZeroSeed); }
+        let taps: Vec<u8> = self.taps().iter().map(|&t| t as u8).collect();
+        let init = bits[..self.length].iter().rev().fold(0u64, |acc, &b| acc << 1 | b as u64);
+        Lfsr::new(&taps, init, self.length as u8)
+    }
+}
+
+/// Unpacks bytes into bits, most significant first, same as `Keystream::next_byte` packs them
+pub fn bits_from_bytes(bytes: &[u8]) -> Vec<u8> {
+    bytes.iter().flat_map(|b| (0..8).rev().map(move |i| b >> i & 1)).collect()
+}
+
+/// Bits packed into words, bit `i` at `words[i / 64] >> (i % 64)`
+pub(crate) fn pack(bits: impl Iterator<Item = u8>) -> Vec<u64> {
+    let mut words = Vec::new();
+    for (i, bit) in bits.enumerate() {
+        if i % 64 == 0 { words.push(0); }
+        words[i / 64] |= (bit as u64) << (i % 64);
+    }
+    words
+}
+
+/// 64 bits of `words` starting at bit `pos`
+pub(crate) fn word_at(words: &[u64], pos: usize) -> u64 {
+    let (i, shift) = (pos / 64, pos % 64);
+    let low = words.get(i).map_or(0, |w| w >> shift);
+    let high = if shift == 0 { 0 } else { words.get(i + 1).map_or(0, |w| w << (64 - shift)) };
+    low | high
+}
+
+/// `dst ^= src << shift`
+fn xor_shifted(dst: &mut Vec<u64>, src: &[u64], shift: usize) {
+    let needed = (src.len() * 64 + shift).div_ceil(64);
+    if dst.len() < needed { dst.resize(needed, 0); }
+    let (words, bits) = (shift / 64, shift % 64);
+    for (i, &w) in src.iter().enumerate() {
+        dst[i + words] ^= w << bits;
+        if bits != 0 && i + words + 1 < dst.len() {
+            dst[i + words + 1] ^= w >> (64 - bits);
+        }
+    }
+}
+
+/// Finds the shortest LFSR generating `bits` with the Berlekamp-Massey algorithm
+pub fn berlekamp_massey(bits: &[u8]) -> LinearComplexity {
+    let n = bits.len();
+    // Sequence reversed, so that s[j-i] for growing i are consecutive bits
+    let reversed = pack(bits.iter().rev().copied());
+    let mut c = vec![1u64];
+    let mut b = vec![1u64];
+    let mut length = 0;
+    let mut shift = 1;
+    let mut profile = Vec::with_capacity(n);
+
+    for j in 0..n {
+        // Discrepancy s[j] + sum c_i * s[j-i], s[j-i] is at n-1-j+i in reversed
+        let words = (length + 64) / 64;
+        let d = (0..words.min(c.len()))
+            .fold(0u64, |acc, k| acc ^ (c[k] & word_at(&reversed, n - 1 - j + 64 * k)))
+            .count_ones() & 1;
+        if d == 0 {
+            shift += 1;
+        } else if 2 * length <= j {
+            let t = c.clone();
+            xor_shifted(&mut c, &b, shift);
+            length = j + 1 - length;
+            b = t;
+            shift = 1;
+        } else {
+            xor_shifted(&mut c, &b, shift);
+            shift += 1;
+        }
+        profile.push(length);
+    }
+
+    let connection = (0..=length).map(|i| (word_at(&c, i) & 1) as u8).collect();
+    LinearComplexity { length, connection, profile }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{primitive_polynomial, Keystream};
+
+    #[test]
+    fn small_sequences_test() {
+        assert_eq!(berlekamp_massey(&[]).length, 0);
+        let zeros = berlekamp_massey(&[0; 10]);
+        assert_eq!((zeros.length, zeros.connection.as_slice()), (0, [1].as_slice()));
+
+        let ones = berlekamp_massey(&[1; 10]);
+        assert_eq!((ones.length, ones.connection.as_slice()), (1, [1, 1].as_slice()));
+
+        let impulse = berlekamp_massey(&[0, 0, 0, 1]);
+        assert_eq!(impulse.length, 4);
+        assert_eq!(impulse.profile, [0, 0, 0, 4]);
+
+        // s[j] = s[j-1] + s[j-3]
+        let seq = berlekamp_massey(&[1, 0, 0, 1, 1, 1, 0, 1, 0, 0, 1, 1, 1, 0]);
+        assert_eq!(seq.length, 3);
+        assert_eq!(seq.connection, [1, 1, 0, 1]);
+        assert_eq!(seq.profile, [1, 1, 1, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3]);
+    }
+
+    #[test]
+    fn recover_lfsr_test() {
+        for n in [5u8, 17, 31, 64] {
+            let mut lfsr = Lfsr::with_polynomial(&primitive_polynomial(n).unwrap(), 0x1234_5678_9ABC_DEF1 >> (64 - n)).unwrap();
+            let mut copy = lfsr.clone();
+            let bits: Vec<u8> = (0..2 * n as usize).map(|_| lfsr.next_bit()).collect();
+
+            let result = berlekamp_massey(&bits);
+            assert_eq!(result.length, n as usize);
+            assert_eq!(result.polynomial().unwrap(), copy.polynomial().unwrap());
+
+            let mut recovered = result.to_lfsr(&bits).unwrap();
+            for _ in 0..1000 {
+                assert_eq!(recovered.next_bit(), copy.next_bit());
+            }
+        }
+    }
+
+    #[test]
+    fn missing_tap_zero_test() {
+        // Tap 0 missing, so the polynomial degree is below the register length
+        let mut lfsr = Lfsr::new(&[1, 2], 0b10111, 5).unwrap();
+        let bits: Vec<u8> = (0..40).map(|_| lfsr.next_bit()).collect();
+        let result = berlekamp_massey(&bits);
+        assert_eq!(result.length, 5);
+        assert_eq!(result.connection, [1, 0, 0, 1, 1, 0]);
+        assert_eq!(result.polynomial(), Err(Error::MissingTapZero));
+        let mut recovered = result.to_lfsr(&bits).unwrap();
+        assert!(bits.iter().all(|&b| b == recovered.next_bit()));
+    }
+
+    #[test]
+    fn to_lfsr_errors_test() {
+        let bits = [1, 0, 0, 1, 1, 1, 0, 1];
+        assert_eq!(berlekamp_massey(&bits).to_lfsr(&bits[..2]).unwrap_err(), Error::KeystreamTooShort(3));
+        assert_eq!(berlekamp_massey(&[0; 10]).to_lfsr(&[0; 10]).unwrap_err(), Error::ZeroSeed);
+        assert_eq!(berlekamp_massey(&[1; 10]).to_lfsr(&[1; 10]).unwrap_err(), Error::TooFewTaps);
+    }
+
+    #[test]
+    fn bytes_test() {
+        assert_eq!(bits_from_bytes(&[0xA5, 0x01]), [1, 0, 1, 0, 0, 1, 0, 1, 0, 0, 0, 0, 0, 0, 0, 1]);
+
+        let mut lfsr = Lfsr::new(&[0, 1, 3, 4], 0xABCDABCD_ABCDABCD, 64).unwrap();
+        let mut bytes = [0u8; 16];
+        lfsr.fill_bytes(&mut bytes);
+        let result = berlekamp_massey(&bits_from_bytes(&bytes));
+        assert_eq!(result.taps(), [0, 1, 3, 4]);
+    }
+
+    #[test]
+    fn random_profile_test() {
+        // Profile of a sequence with no short register follows n/2
+        let mut x = 12345u64;
+        let bits: Vec<u8> = (0..2000).map(|_| {
+            x = x.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            (x >> 63) as u8
+        }).collect();
+        let result = berlekamp_massey(&bits);
+        assert_eq!(result.length, 998);
+        assert!(result.profile.windows(2).all(|w| w[0] <= w[1]));
+        assert_eq!(*result.profile.last().unwrap(), result.length);
+    }
+}
@@ -0,0 +1,140 @@
+use crate::{Anf, Error};
+
+/// Largest number of variables of a truth table
+const MAX_VARIABLES: u32 = 20;
+
+/// Boolean function of `n` variables given by its `2^n` values,
+/// `values[x]` is the value with variable `i` set to bit `i` of `x`.
+///
+/// Cryptographic properties of filter and combining functions are computed
+/// from the Walsh spectrum `W(a) = sum((-1)^(f(x) + a.x))`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TruthTable {
+    vars: u32,
+    values: Vec<u8>,
+}
+
+impl TruthTable {
+    /// Table from values of 0 and 1, their number should be a power of two
+    pub fn new(values: &[u8]) -> Result<Self, Error> {
+        if !values.len().is_power_of_two() || values.len() > 1 << MAX_VARIABLES {
+            return Err(Error::InvalidFunction(format!("truth table of {} values isn't 2^n for n up to {}", values.len(), MAX_VARIABLES)));
+        }
+        if values.iter().any(|&v| v > 1) {
+            return Err(Error::InvalidFunction("truth table should only have 0 and 1".to_owned()));
+        }
+        Ok(TruthTable { vars: values.len().trailing_zeros(), values: values.to_vec() })
+    }
+
+    /// Table of `anf` over the variables it uses, the lowest used variable becomes variable 0 and so on
+    pub fn from_anf(anf: &Anf) -> Result<Self, Error> {
+        let used = anf.variables();
+        if used.count_ones() > MAX_VARIABLES {
+            return Err(Error::InvalidFunction(format!("function has more than {} variables", MAX_VARIABLES)));
+        }
+        let positions: Vec<u32> = (0..64).filter(|i| used >> i & 1 == 1).collect();
+        let values: Vec<u8> = (0..1u64 << positions.len())
+            .map(|x| anf.eval(positions.iter().enumerate().fold(0, |acc, (i, &p)| acc | (x >> i & 1) << p)))
+            .collect();
+        TruthTable::new(&values)
+    }
+
+    pub fn vars(&self) -> u32 {
+        self.vars
+    }
+
+    pub fn values(&self) -> &[u8] {
+        &self.values
+    }
+
+    /// Algebraic normal form by the Moebius transform, variables numbered as in the table
+    pub fn anf(&self) -> Anf {
+        let mut coeffs = self.values.clone();
+        for i in 0..self.vars {
+            for x in 0..coeffs.len() {
+                if x >> i & 1 == 1 { coeffs[x] ^= coeffs[x ^ 1 << i]; }
+            }
+        }
+        let monomials: Vec<u64> = (0..coeffs.len() as u64).filter(|&m| coeffs[m as usize] == 1).collect();
+        Anf::from_monomials(&monomials)
+    }
+
+    /// Algebraic degree, the largest monomial of the normal form
+    pub fn degree(&self) -> u32 {
+        self.anf().degree()
+    }
+
+    /// Walsh spectrum by the fast Walsh-Hadamard transform
+    pub fn walsh(&self) -> Vec<i64> {
+        let mut w: Vec<i64> = self.values.iter().map(|&v| 1 - 2 * v as i64).collect();
+        let mut half = 1;
+        while half < w.len() {
+            for start in (0..w.len()).step_by(2 * half) {
+                for x in start..start + half {
+                    (w[x], w[x + half]) = (w[x] + w[x + half], w[x] - w[x + half]);
+                }
+            }
+            half *= 2;
+        }
+        w
+    }
+
+    /// Distance to the closest affine function, `2^(n-1) - max|W(a)| / 2`
+    pub fn nonlinearity(&self) -> u64 {
+        let max = self.walsh().iter().map(|w| w.unsigned_abs()).max().unwrap();
+        (1 << self.vars) / 2 - max / 2
+    }
+
+    /// Largest `m` such that the output is independent of any `m` inputs,
+    /// that is `W(a) = 0` for all `a` of weight 1 to `m`
+    pub fn correlation_immunity(&self) -> u32 {
+        let w = self.walsh();
+        (1..=self.vars)
+            .take_while(|&m| (1..w.len()).filter(|a| a.count_ones() == m).all(|a| w[a] == 0))
+            .count() as u32
+    }
+
+    /// Whether the function outputs as many 0 as 1
+    pub fn is_balanced(&self) -> bool {
+        self.walsh()[0] == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table(anf: &str) -> TruthTable {
+        TruthTable::from_anf(&anf.parse().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn properties_test() {
+        // (nonlinearity, correlation immunity, balanced, degree)
+        let props = |t: &TruthTable| (t.nonlinearity(), t.correlation_immunity(), t.is_balanced(), t.degree());
+        // Geffe combiner and majority
+        assert_eq!(props(&table("x0*x1 + x1*x2 + x2")), (2, 0, true, 2));
+        assert_eq!(props(&table("x0*x1 + x0*x2 + x1*x2")), (2, 0, true, 2));
+        // Linear function is immune to any 2 of its 3 inputs, but has no nonlinearity
+        assert_eq!(props(&table("x0 + x1 + x2")), (0, 2, true, 1));
+        // Bent function has the highest nonlinearity and isn't balanced
+        assert_eq!(props(&table("x0*x1 + x2*x3")), (6, 0, false, 2));
+        assert_eq!(props(&table("x0 + x1 + x2*x3")), (4, 1, true, 2));
+    }
+
+    #[test]
+    fn anf_test() {
+        let t = TruthTable::new(&[0, 0, 0, 1, 0, 1, 1, 1]).unwrap();
+        assert_eq!(t.anf().to_string(), "x0*x1 + x0*x2 + x1*x2");
+        assert_eq!(t.vars(), 3);
+        assert_eq!(t.walsh(), [0, 4, 4, 0, 4, 0, 0, -4]);
+
+        // Variables of the normal form are renumbered in the table
+        let t = table("x3*x10 + x7 + 1");
+        assert_eq!(t.vars(), 3);
+        assert_eq!(t.anf().to_string(), "x0*x2 + x1 + 1");
+
+        assert!(TruthTable::new(&[0, 1, 1]).is_err());
+        assert!(TruthTable::new(&[0, 2]).is_err());
+    }
+}
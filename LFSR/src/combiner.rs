@@ -0,0 +1,217 @@
+//! Classic keystream generators combining several registers nonlinearly,
+//! which hides the linear structure a single register has.
+
+use crate::{Error, Keystream, Lfsr, Register};
+
+/// Geffe generator, outputs `x1*x2 + x2*x3 + x3` of three register outputs:
+/// the second register selects the first one when it is 1, the third one otherwise.
+///
+/// Output agrees with `x1` and with `x3` 3/4 of the time, which makes it
+/// weak against correlation attacks.
+#[derive(Clone, Debug)]
+pub struct Geffe<K = Lfsr> {
+    pub x1: K,
+    pub x2: K,
+    pub x3: K,
+}
+
+impl<K: Keystream> Geffe<K> {
+    pub fn new(x1: K, x2: K, x3: K) -> Self {
+        Geffe { x1, x2, x3 }
+    }
+}
+
+impl<K: Keystream> Keystream for Geffe<K> {
+    fn next_bit(&mut self) -> u8 {
+        let (x1, x2, x3) = (self.x1.next_bit(), self.x2.next_bit(), self.x3.next_bit());
+        x1 & x2 ^ x2 & x3 ^ x3
+    }
+}
+
+/// Shrinking generator, outputs the bit of `data` whenever `selector` outputs 1,
+/// both registers are clocked together and the output is irregular
+#[derive(Clone, Debug)]
+pub struct ShrinkingGenerator<K = Lfsr> {
+    data: K,
+    selector: K,
+}
+
+impl<K: Register> ShrinkingGenerator<K> {
+    /// Combines two registers, the selector needs tap 0: without it the register
+    /// can reach the all zero state and the generator would never output again
+    pub fn new(data: K, selector: K) -> Result<Self, Error> {
+        if !selector.has_tap_zero() { return Err(Error::MissingTapZero); }
+        Ok(ShrinkingGenerator { data, selector })
+    }
+}
+
+impl<K: Keystream> Keystream for ShrinkingGenerator<K> {
+    fn next_bit(&mut self) -> u8 {
+        loop {
+            let bit = self.data.next_bit();
+            if self.selector.next_bit() == 1 { return bit; }
+        }
+    }
+}
+
+/// Self-shrinking generator, takes output of a single register in pairs
+/// and outputs the second bit of a pair when the first is 1
+#[derive(Clone, Debug)]
+pub struct SelfShrinkingGenerator<K = Lfsr> {
+    lfsr: K,
+}
+
+impl<K: Register> SelfShrinkingGenerator<K> {
+    /// Register needs tap 0 for the same reason as the `ShrinkingGenerator` selector
+    pub fn new(lfsr: K) -> Result<Self, Error> {
+        if !lfsr.has_tap_zero() { return Err(Error::MissingTapZero); }
+        Ok(SelfShrinkingGenerator { lfsr })
+    }
+}
+
+impl<K: Keystream> Keystream for SelfShrinkingGenerator<K> {
+    fn next_bit(&mut self) -> u8 {
+        loop {
+            let select = self.lfsr.next_bit();
+            let bit = self.lfsr.next_bit();
+            if select == 1 { return bit; }
+        }
+    }
+}
+
+/// Alternating step generator, `control` decides which of the two other registers
+/// is clocked, 1 clocks `x1` and 0 clocks `x2`. Output is the xor of the last bits
+/// produced by `x1` and `x2`, the register not clocked repeats its bit (0 at the start).
+#[derive(Clone, Debug)]
+pub struct AlternatingStepGenerator<K = Lfsr> {
+    pub control: K,
+    pub x1: K,
+    pub x2: K,
+    last: (u8, u8),
+}
+
+impl<K: Keystream> AlternatingStepGenerator<K> {
+    pub fn new(control: K, x1: K, x2: K) -> Self {
+        AlternatingStepGenerator { control, x1, x2, last: (0, 0) }
+    }
+}
+
+impl<K: Keystream> Keystream for AlternatingStepGenerator<K> {
+    fn next_bit(&mut self) -> u8 {
+        if self.control.next_bit() == 1 {
+            self.last.0 = self.x1.next_bit();
+        } else {
+            self.last.1 = self.x2.next_bit();
+        }
+        self.last.0 ^ self.last.1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{xor_stream, GaloisLfsr, LongLfsr};
+
+    // Periods 15, 31 and 7
+    fn r4() -> Lfsr { Lfsr::new(&[0, 1], 1, 4).unwrap() }
+    fn r5() -> Lfsr { Lfsr::new(&[0, 2], 1, 5).unwrap() }
+    fn r3() -> Lfsr { Lfsr::new(&[0, 1], 1, 3).unwrap() }
+
+    fn bits<K: Keystream>(gen: &mut K, count: usize) -> Vec<u8> {
+        (0..count).map(|_| gen.next_bit()).collect()
+    }
+
+    #[test]
+    fn by_hand_test() {
+        // Outputs worked out from the recurrences s[t+4] = s[t] + s[t+1],
+        // s[t+5] = s[t] + s[t+2] and s[t+3] = s[t] + s[t+1], starting from 1, 0, 0, ...
+        let x1 = [1, 0, 0, 0, 1, 0, 0, 1, 1, 0, 1, 0, 1, 1, 1];
+        let x2 = [1, 0, 0, 0, 0, 1, 0, 0, 1, 0, 1, 1, 0, 0, 1];
+        let x3 = [1, 0, 0, 1, 0, 1, 1, 1, 0, 0, 1, 0, 1, 1, 1];
+        assert_eq!((bits(&mut r4(), 15), bits(&mut r5(), 15), bits(&mut r3(), 15)), (x1.to_vec(), x2.to_vec(), x3.to_vec()));
+
+        // Geffe takes x1 where x2 is 1 and x3 elsewhere
+        assert_eq!(bits(&mut Geffe::new(r4(), r5(), r3()), 15), [1, 0, 0, 1, 0, 0, 1, 1, 1, 0, 1, 0, 1, 1, 1]);
+        // Shrinking generator keeps x1 at t = 0, 5, 8, 10, 11 and 14 where x2 is 1
+        assert_eq!(bits(&mut ShrinkingGenerator::new(r4(), r5()).unwrap(), 6), [1, 0, 1, 1, 0, 1]);
+        // Self-shrinking generator on x2 reads pairs 10 00 01 00 10 11 00, keeping 0, 0 and 1
+        assert_eq!(bits(&mut SelfShrinkingGenerator::new(r5()).unwrap(), 3), [0, 0, 1]);
+    }
+
+    #[test]
+    fn geffe_test() {
+        let mut geffe = Geffe::new(r4(), r5(), r3());
+        assert_eq!(bits(&mut geffe, 20), [1, 0, 0, 1, 0, 0, 1, 1, 1, 0, 1, 0, 1, 1, 1, 1, 0, 0, 0, 1]);
+
+        // Correlation with the first and the third register over the full period
+        let mut geffe = Geffe::new(r4(), r5(), r3());
+        let (mut x1, mut x3) = (r4(), r3());
+        let period = 15 * 31 * 7;
+        let (mut same1, mut same3) = (0, 0);
+        for _ in 0..period {
+            let z = geffe.next_bit();
+            same1 += (z == x1.next_bit()) as u32;
+            same3 += (z == x3.next_bit()) as u32;
+        }
+        assert_eq!((same1, same3), (2475, 2423));
+        assert!((same1 as f64 / period as f64 - 0.75).abs() < 0.02);
+    }
+
+    #[test]
+    fn shrinking_test() {
+        let mut gen = ShrinkingGenerator::new(r4(), r5()).unwrap();
+        assert_eq!(bits(&mut gen, 20), [1, 0, 1, 1, 0, 1, 1, 0, 0, 0, 1, 1, 1, 0, 1, 1, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn self_shrinking_test() {
+        let mut gen = SelfShrinkingGenerator::new(r5()).unwrap();
+        assert_eq!(bits(&mut gen, 20), [0, 0, 1, 1, 1, 0, 1, 1, 0, 0, 1, 1, 0, 1, 0, 0, 0, 0, 1, 1]);
+    }
+
+    #[test]
+    fn zero_state_selector_test() {
+        // Without tap 0 this selector reaches 0 and stays there
+        let selector = Lfsr::new(&[1, 2], 1, 5).unwrap();
+        assert_eq!(ShrinkingGenerator::new(r4(), selector.clone()).unwrap_err(), Error::MissingTapZero);
+        assert_eq!(SelfShrinkingGenerator::new(selector).unwrap_err(), Error::MissingTapZero);
+        let galois = GaloisLfsr::new(0b0_1100, 1, 5).unwrap();
+        assert_eq!(SelfShrinkingGenerator::new(galois).unwrap_err(), Error::MissingTapZero);
+    }
+
+    #[test]
+    fn other_registers_test() {
+        let expected = bits(&mut ShrinkingGenerator::new(r4(), r5()).unwrap(), 50);
+        let galois = ShrinkingGenerator::new(GaloisLfsr::from(&r4()), GaloisLfsr::from(&r5()));
+        assert_eq!(bits(&mut galois.unwrap(), 50), expected);
+        let long = ShrinkingGenerator::new(LongLfsr::from(&r4()), LongLfsr::from(&r5()));
+        assert_eq!(bits(&mut long.unwrap(), 50), expected);
+
+        let expected = bits(&mut SelfShrinkingGenerator::new(r5()).unwrap(), 50);
+        assert_eq!(bits(&mut SelfShrinkingGenerator::new(GaloisLfsr::from(&r5())).unwrap(), 50), expected);
+    }
+
+    #[test]
+    fn alternating_step_test() {
+        let mut gen = AlternatingStepGenerator::new(r3(), r4(), r5());
+        assert_eq!(bits(&mut gen, 20), [1, 0, 1, 0, 0, 0, 0, 1, 1, 1, 0, 1, 1, 0, 0, 1, 1, 0, 1, 0]);
+    }
+
+    #[test]
+    fn xor_stream_test() {
+        let plain: Vec<u8> = (0..1000u32).map(|i| (i * 31) as u8).collect();
+        let lfsr = |taps: &[u8], seed| Lfsr::new(taps, seed, 64).unwrap();
+        let make = || Geffe::new(lfsr(&[0, 1, 3, 4], 0x1234), lfsr(&[0, 60, 61, 63], 0x5678), lfsr(&[0, 1, 3, 4], 0x9ABC));
+        let mut encrypted = Vec::new();
+        let mut decrypted = Vec::new();
+        xor_stream(&mut plain.as_slice(), &mut encrypted, &mut make()).unwrap();
+        assert_ne!(plain, encrypted);
+        xor_stream(&mut encrypted.as_slice(), &mut decrypted, &mut make()).unwrap();
+        assert_eq!(plain, decrypted);
+
+        let mut shrinking = ShrinkingGenerator::new(lfsr(&[0, 1, 3, 4], 0x1234), lfsr(&[0, 60, 61, 63], 0x5678)).unwrap();
+        let mut encrypted = Vec::new();
+        xor_stream(&mut plain.as_slice(), &mut encrypted, &mut shrinking).unwrap();
+        assert_ne!(plain, encrypted);
+    }
+}
@@ -0,0 +1,57 @@
+use crate::Error;
+
+/// Longest register whose state graph is explored state by state
+pub const MAX_CYCLE_LENGTH: u8 = 24;
+
+/// Cycles in the state graph of a register, every state leads into one of them
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CycleStructure {
+    /// Length and smallest state of each cycle, shortest first
+    pub cycles: Vec<(u64, u64)>,
+    /// Number of states not on any cycle, zero when every state has exactly one predecessor
+    pub tail_states: u64,
+}
+
+impl CycleStructure {
+    /// Whether the state update is invertible, so the states split into disjoint cycles
+    pub fn is_permutation(&self) -> bool {
+        self.tail_states == 0
+    }
+
+    /// Number of cycles of each length, shortest first
+    pub fn counts(&self) -> Vec<(u64, usize)> {
+        let mut counts: Vec<(u64, usize)> = Vec::new();
+        for &(len, _) in &self.cycles {
+            match counts.last_mut() {
+                Some((last, count)) if *last == len => *count += 1,
+                _ => counts.push((len, 1)),
+            }
+        }
+        counts
+    }
+}
+
+/// Walks all `2^length` states following `next` and finds the cycles
+pub(crate) fn cycle_structure(length: u8, next: impl Fn(u64) -> u64) -> Result<CycleStructure, Error> {
+    if length == 0 || length > MAX_CYCLE_LENGTH { return Err(Error::InvalidLength(length as u32)); }
+    // 0 not visited, 1 on the current path, 2 done
+    let mut color = vec![0u8; 1 << length];
+    let mut cycles = Vec::new();
+    let mut path = Vec::new();
+    for start in 0..1u64 << length {
+        let mut s = start;
+        while color[s as usize] == 0 {
+            color[s as usize] = 1;
+            path.push(s);
+            s = next(s);
+        }
+        if color[s as usize] == 1 {
+            let pos = path.iter().rposition(|&p| p == s).unwrap();
+            cycles.push(((path.len() - pos) as u64, *path[pos..].iter().min().unwrap()));
+        }
+        path.drain(..).for_each(|p| color[p as usize] = 2);
+    }
+    cycles.sort_unstable();
+    let on_cycles: u64 = cycles.iter().map(|&(len, _)| len).sum();
+    Ok(CycleStructure { cycles, tail_states: (1 << length) - on_cycles })
+}
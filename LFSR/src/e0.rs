@@ -0,0 +1,118 @@
+use crate::{Error, Keystream, Lfsr, Polynomial};
+
+/// Feedback polynomials of the four E0 registers, 25, 31, 33 and 39 bits long
+const TERMS: [&[u32]; 4] = [&[25, 20, 12, 8], &[31, 24, 16, 12], &[33, 28, 24, 4], &[39, 36, 28, 4]];
+/// Output positions 24, 24, 32 and 32 counted from the input end of each register
+const OUTPUT_BITS: [u8; 4] = [0, 6, 0, 6];
+
+/// Keystream generator of the Bluetooth E0 cipher: four registers combined by a
+/// summation combiner with 4 bits of memory.
+///
+/// This is the core generator starting from given register contents, key and clock
+/// loading of the Bluetooth spec produce these contents. It isn't checked against the
+/// sample data of the spec, so the tap and output bit conventions are unverified.
+#[derive(Clone, Debug)]
+pub struct E0 {
+    lfsrs: [Lfsr; 4],
+    /// Combiner state `c_t` and `c_(t-1)`, two bits each
+    c: u8,
+    c_prev: u8,
+}
+
+/// `T2` blend mapping of the combiner, `(x1, x0) -> (x0, x1 + x0)`
+fn t2(c: u8) -> u8 {
+    (c & 1) << 1 | ((c >> 1) ^ c) & 1
+}
+
+/// One step of the combiner, returns output bit and the next `c`
+fn combine(x: [u8; 4], c: u8, c_prev: u8) -> (u8, u8) {
+    let sum = x.iter().sum::<u8>();
+    let z = x.iter().fold(c & 1, |acc, b| acc ^ b);
+    let s = (sum + c) / 2;
+    (z, s ^ c ^ t2(c_prev))
+}
+
+impl E0 {
+    /// Generator with registers initialized with `init` and combiner state `c`, `c_prev` (2 bits each)
+    pub fn new(init: [u64; 4], c: u8, c_prev: u8) -> Result<Self, Error> {
+        let lfsr = |i: usize| Lfsr::with_polynomial(&Polynomial::from_terms(TERMS[i])?, init[i]);
+        Ok(E0 {
+            lfsrs: [lfsr(0)?, lfsr(1)?, lfsr(2)?, lfsr(3)?],
+            c: c & 3,
+            c_prev: c_prev & 3,
+        })
+    }
+}
+
+impl Keystream for E0 {
+    fn next_bit(&mut self) -> u8 {
+        let x: [u8; 4] = std::array::from_fn(|i| self.lfsrs[i].bit(OUTPUT_BITS[i]));
+        let (z, c) = combine(x, self.c, self.c_prev);
+        (self.c_prev, self.c) = (self.c, c);
+        self.lfsrs.iter_mut().for_each(|r| { r.next_bit(); });
+        z
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn polynomials_test() {
+        for terms in TERMS {
+            assert!(Polynomial::from_terms(terms).unwrap().is_primitive().unwrap());
+        }
+    }
+
+    #[test]
+    fn combiner_test() {
+        // y = 4, c = 0: s = 2, z = 0, c' = 2
+        assert_eq!(combine([1, 1, 1, 1], 0, 0), (0, 2));
+        // y = 0, c = 2: s = 1, z = 0, c' = 1 + 2 + T2(0) = 3
+        assert_eq!(combine([0, 0, 0, 0], 2, 0), (0, 3));
+        // y = 1, c = 3, c_prev = 2: s = 2, z = 1 + 1, c' = 2 + 3 + T2(2) = 2 + 3 + 1 = 0
+        assert_eq!(combine([1, 0, 0, 0], 3, 2), (0, 0));
+        assert_eq!((0..4).map(t2).collect::<Vec<_>>(), [0, 3, 1, 2]);
+    }
+
+    /// Bit level model of the generator: registers as lists of bits `s_t, s_(t+1), ...`
+    /// with `s_(t+n) = s_t + sum(s_(t+n-e))` over the terms `e`, and the summation
+    /// combiner computed from the bits of `c` one by one.
+    fn model(init: [u64; 4], c: u8, c_prev: u8, count: usize) -> Vec<u8> {
+        let mut regs: Vec<Vec<u8>> = (0..4).map(|i| {
+            (0..TERMS[i][0]).map(|j| (init[i] >> j & 1) as u8).collect()
+        }).collect();
+        let (mut c1, mut c0) = (c >> 1 & 1, c & 1);
+        let (mut p1, mut p0) = (c_prev >> 1 & 1, c_prev & 1);
+        let mut bits = Vec::new();
+        for t in 0..count {
+            let x: Vec<u8> = (0..4).map(|i| regs[i][t + OUTPUT_BITS[i] as usize]).collect();
+            bits.push(x[0] ^ x[1] ^ x[2] ^ x[3] ^ c0);
+            let s = (x.iter().sum::<u8>() + 2 * c1 + c0) / 2;
+            // c_(t+1) = s_(t+1) + T1(c_t) + T2(c_(t-1)), T2(p1, p0) = (p0, p1 + p0)
+            let next = (s >> 1 ^ c1 ^ p0, s & 1 ^ c0 ^ p1 ^ p0);
+            (p1, p0) = (c1, c0);
+            (c1, c0) = next;
+            for (i, reg) in regs.iter_mut().enumerate() {
+                let n = TERMS[i][0] as usize;
+                let feedback = TERMS[i][1..].iter().fold(reg[t], |acc, &e| acc ^ reg[t + n - e as usize]);
+                reg.push(feedback);
+            }
+        }
+        bits
+    }
+
+    #[test]
+    fn keystream_test() {
+        let init = [0x1A2_B3C4, 0x5A5A_5A5A, 0x1_DEAD_BEEF, 0x55_AA55_AA55];
+        for (c, c_prev) in [(0, 0), (1, 2), (3, 3)] {
+            let mut e0 = E0::new(init, c, c_prev).unwrap();
+            let mut bytes = [0u8; 32];
+            e0.fill_bytes(&mut bytes);
+            let bits = model(init, c, c_prev, 8 * bytes.len());
+            assert_eq!(bytes.to_vec(), bits.chunks(8).map(|b| b.iter().fold(0, |acc, bit| acc << 1 | bit)).collect::<Vec<u8>>());
+        }
+        assert_eq!(E0::new([0, 1, 1, 1], 0, 0).unwrap_err(), Error::ZeroSeed);
+    }
+}
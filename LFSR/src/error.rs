@@ -0,0 +1,55 @@
+use std::fmt;
+
+/// Invalid register configuration
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// Length is 0 or more than the register can hold
+    InvalidLength(u32),
+    /// All zero register never leaves this state
+    ZeroSeed,
+    /// Seed has bits set above the register length
+    SeedTooWide,
+    /// Tap doesn't point inside the register
+    TapOutOfRange(u32),
+    /// Bit of a Galois mask above the register length, which no tap stands for
+    MaskBitOutOfRange(u32),
+    /// Same tap given twice, which would cancel itself out
+    DuplicateTap(u32),
+    /// Feedback needs at least two taps
+    TooFewTaps,
+    /// Register without tap 0 has no feedback polynomial of its length and may fall
+    /// into the all zero state, which a selector never leaves
+    MissingTapZero,
+    /// Polynomial can't be parsed or used for a register
+    InvalidPolynomial(String),
+    /// Feedback polynomial doesn't give the maximal period
+    NotPrimitive,
+    /// Boolean function can't be parsed or used for a register
+    InvalidFunction(String),
+    /// Keystream is too short to determine the register or run a test, holds the number of bits needed
+    KeystreamTooShort(usize),
+    /// No seed of the register is significantly correlated with the keystream or explains it
+    SeedNotFound,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::InvalidLength(len) => write!(f, "register length {} is out of range", len),
+            Error::ZeroSeed => write!(f, "seed shouldn't be 0"),
+            Error::SeedTooWide => write!(f, "seed is wider than the register"),
+            Error::TapOutOfRange(tap) => write!(f, "tap {} is outside of the register", tap),
+            Error::MaskBitOutOfRange(bit) => write!(f, "mask bit {} is outside of the register", bit),
+            Error::DuplicateTap(tap) => write!(f, "tap {} is given more than once", tap),
+            Error::TooFewTaps => write!(f, "should provide at least two taps"),
+            Error::MissingTapZero => write!(f, "register should have tap 0"),
+            Error::InvalidPolynomial(msg) => write!(f, "invalid polynomial: {}", msg),
+            Error::NotPrimitive => write!(f, "feedback polynomial is not primitive"),
+            Error::InvalidFunction(msg) => write!(f, "invalid Boolean function: {}", msg),
+            Error::KeystreamTooShort(bits) => write!(f, "need at least {} bits of keystream", bits),
+            Error::SeedNotFound => write!(f, "no seed of the register fits the keystream"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
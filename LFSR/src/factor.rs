@@ -0,0 +1,103 @@
+//! Integer factorization for numbers up to 64 bits, such as `2^n - 1`.
+
+fn mul_mod(a: u64, b: u64, m: u64) -> u64 {
+    ((a as u128 * b as u128) % m as u128) as u64
+}
+
+fn pow_mod(a: u64, e: u64, m: u64) -> u64 {
+    let mut result = 1 % m;
+    let mut base = a % m;
+    let mut e = e;
+    while e != 0 {
+        if e & 1 == 1 { result = mul_mod(result, base, m); }
+        base = mul_mod(base, base, m);
+        e >>= 1;
+    }
+    result
+}
+
+/// Miller-Rabin test, deterministic for 64 bit numbers with these bases
+pub(crate) fn is_prime(n: u64) -> bool {
+    const BASES: [u64; 12] = [2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37];
+    if n < 2 { return false; }
+    if let Some(&p) = BASES.iter().find(|&&p| n.is_multiple_of(p)) { return n == p; }
+    let s = (n - 1).trailing_zeros();
+    let d = (n - 1) >> s;
+    BASES.iter().all(|&a| {
+        let mut x = pow_mod(a, d, n);
+        if x == 1 || x == n - 1 { return true; }
+        (1..s).any(|_| {
+            x = mul_mod(x, x, n);
+            x == n - 1
+        })
+    })
+}
+
+pub(crate) fn gcd(a: u64, b: u64) -> u64 {
+    if b == 0 { a } else { gcd(b, a % b) }
+}
+
+/// Some non-trivial divisor of odd composite `n` by Pollard's rho
+fn pollard_rho(n: u64) -> u64 {
+    for c in 1.. {
+        let f = |x: u64| (mul_mod(x, x, n) + c) % n;
+        let (mut x, mut y, mut d) = (2u64, 2u64, 1u64);
+        while d == 1 {
+            x = f(x);
+            y = f(f(y));
+            d = gcd(x.abs_diff(y), n);
+        }
+        if d != n { return d; }
+    }
+    unreachable!()
+}
+
+/// Prime factors of `n` with their multiplicities, in ascending order
+pub(crate) fn factorize(n: u64) -> Vec<(u64, u32)> {
+    let mut primes = Vec::new();
+    let mut stack = vec![n];
+    while let Some(mut m) = stack.pop() {
+        for p in [2u64, 3, 5, 7] {
+            while m % p == 0 {
+                primes.push(p);
+                m /= p;
+            }
+        }
+        if m == 1 { continue; }
+        if is_prime(m) {
+            primes.push(m);
+        } else {
+            let d = pollard_rho(m);
+            stack.push(d);
+            stack.push(m / d);
+        }
+    }
+    primes.sort_unstable();
+    let mut factors: Vec<(u64, u32)> = Vec::new();
+    for p in primes {
+        match factors.last_mut() {
+            Some((q, k)) if *q == p => *k += 1,
+            _ => factors.push((p, 1)),
+        }
+    }
+    factors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn factorize_test() {
+        assert_eq!(factorize(1), []);
+        assert_eq!(factorize(360), [(2, 3), (3, 2), (5, 1)]);
+        assert_eq!(factorize(u64::MAX), [(3, 1), (5, 1), (17, 1), (257, 1), (641, 1), (65537, 1), (6700417, 1)]);
+        assert_eq!(factorize((1 << 59) - 1), [(179951, 1), (3203431780337, 1)]);
+        assert_eq!(factorize((1 << 61) - 1), [((1 << 61) - 1, 1)]);
+        for n in 2..=64 {
+            let m = u64::MAX >> (64 - n);
+            assert_eq!(factorize(m).iter().map(|&(p, k)| p.pow(k)).product::<u64>(), m);
+            assert!(factorize(m).iter().all(|&(p, _)| is_prime(p)));
+        }
+    }
+}
@@ -0,0 +1,70 @@
+use crate::{Anf, Error, Keystream, Lfsr, TruthTable};
+
+/// Filter generator: instead of its lowest bit, the register outputs a nonlinear
+/// Boolean function of its state, with `xi` being bit `i` of the register
+#[derive(Clone, Debug)]
+pub struct FilterGenerator {
+    lfsr: Lfsr,
+    filter: Anf,
+}
+
+impl FilterGenerator {
+    pub fn new(lfsr: Lfsr, filter: Anf) -> Result<Self, Error> {
+        let vars = filter.variables();
+        if lfsr.length() < 64 && vars >> lfsr.length() != 0 {
+            return Err(Error::TapOutOfRange(63 - vars.leading_zeros()));
+        }
+        Ok(FilterGenerator { lfsr, filter })
+    }
+
+    pub fn lfsr(&self) -> &Lfsr {
+        &self.lfsr
+    }
+
+    pub fn filter(&self) -> &Anf {
+        &self.filter
+    }
+
+    /// Truth table of the filter over the register bits it uses
+    pub fn truth_table(&self) -> Result<TruthTable, Error> {
+        TruthTable::from_anf(&self.filter)
+    }
+}
+
+impl Lfsr {
+    /// Filter generator outputting `filter` of the register state
+    pub fn with_filter(self, filter: Anf) -> Result<FilterGenerator, Error> {
+        FilterGenerator::new(self, filter)
+    }
+}
+
+impl Keystream for FilterGenerator {
+    fn next_bit(&mut self) -> u8 {
+        let bit = self.filter.eval(self.lfsr.state());
+        self.lfsr.next_bit();
+        bit
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn filter_test() {
+        let lfsr = Lfsr::new(&[0, 2], 1, 5).unwrap();
+        let mut gen = lfsr.clone().with_filter("x0*x2 + x1 + x3*x4".parse().unwrap()).unwrap();
+        let bits: Vec<u8> = (0..24).map(|_| gen.next_bit()).collect();
+        assert_eq!(bits, [0, 0, 0, 0, 1, 0, 0, 0, 1, 1, 1, 1, 1, 0, 1, 0, 0, 1, 0, 1, 0, 1, 0, 0]);
+
+        let table = gen.truth_table().unwrap();
+        assert_eq!((table.vars(), table.degree(), table.nonlinearity()), (5, 2, 12));
+
+        // Filter x0 is the plain register
+        let mut plain = lfsr.clone().with_filter("x0".parse().unwrap()).unwrap();
+        let mut copy = lfsr.clone();
+        assert!((0..100).all(|_| plain.next_bit() == copy.next_bit()));
+
+        assert_eq!(lfsr.with_filter("x0 + x5".parse().unwrap()).unwrap_err(), Error::TapOutOfRange(5));
+    }
+}
@@ -0,0 +1,157 @@
+use crate::{check_register, Error, Keystream, Lfsr, Register};
+
+/// Linear feedback shift register in Galois form, up to 64 bits long.
+///
+/// The register outputs its lowest bit and shifts right,
+/// `mask` is xored into the register whenever the output bit is 1.
+///
+/// Register with mask from `taps_to_mask` produces the same sequence as
+/// a Fibonacci register with these taps, but takes a single xor per step.
+#[derive(Clone, Debug)]
+pub struct GaloisLfsr {
+    reg: u64,
+    mask: u64,
+    bit_len: u8,
+}
+
+impl GaloisLfsr {
+    /// Creates a register of `length` bits, initialized with `init`
+    pub fn new(mask: u64, init: u64, length: u8) -> Result<Self, Error> {
+        check_register(init, length)?;
+        if length < 64 && mask >> length != 0 {
+            return Err(Error::MaskBitOutOfRange(63 - mask.leading_zeros()));
+        }
+        if mask.count_ones() < 2 { return Err(Error::TooFewTaps); }
+        Ok(GaloisLfsr {
+            reg: init,
+            mask,
+            bit_len: length,
+        })
+    }
+
+    /// Current contents of the register
+    pub fn state(&self) -> u64 {
+        self.reg
+    }
+
+    pub fn mask(&self) -> u64 {
+        self.mask
+    }
+
+    pub fn length(&self) -> u8 {
+        self.bit_len
+    }
+}
+
+impl Keystream for GaloisLfsr {
+    fn next_bit(&mut self) -> u8 {
+        let bit = self.reg & 1;
+        self.reg >>= 1;
+        self.reg ^= self.mask & bit.wrapping_neg();
+        bit as u8
+    }
+}
+
+impl Register for GaloisLfsr {
+    /// Tap 0 is the top bit of the mask
+    fn has_tap_zero(&self) -> bool {
+        self.mask >> (self.bit_len - 1) & 1 == 1
+    }
+}
+
+/// Galois mask equivalent to Fibonacci `taps` of a register of `length` bits.
+/// Both describe feedback polynomial `1 + sum(x^(length - tap))`, mask bit `b`
+/// standing for `x^(b + 1)` as in `Polynomial::from_mask`.
+pub fn taps_to_mask(taps: &[u8], length: u8) -> u64 {
+    taps.iter().fold(0u64, |mask, &tap| mask | 1 << (length - 1 - tap))
+}
+
+/// Fibonacci taps equivalent to Galois `mask`, inverse of `taps_to_mask`
+pub fn mask_to_taps(mask: u64, length: u8) -> Vec<u8> {
+    (0..length).rev()
+        .filter(|bit| mask >> bit & 1 == 1)
+        .map(|bit| length - 1 - bit)
+        .collect()
+}
+
+impl From<&Lfsr> for GaloisLfsr {
+    /// Galois register that continues the output of `lfsr`
+    fn from(lfsr: &Lfsr) -> Self {
+        let mask = taps_to_mask(lfsr.taps(), lfsr.length());
+        // Next `length` outputs of the Fibonacci register are its state bits,
+        // pick the Galois state that outputs the same
+        let mut reg = 0u64;
+        for k in 0..lfsr.length() {
+            let mut bit = lfsr.state() >> k & 1;
+            for j in 0..k {
+                bit ^= mask >> (k - 1 - j) & lfsr.state() >> j & 1;
+            }
+            reg |= bit << k;
+        }
+        GaloisLfsr { reg, mask, bit_len: lfsr.length() }
+    }
+}
+
+impl From<&GaloisLfsr> for Lfsr {
+    /// Fibonacci register that continues the output of `lfsr`
+    fn from(lfsr: &GaloisLfsr) -> Self {
+        let mut copy = lfsr.clone();
+        let reg = (0..lfsr.length()).fold(0u64, |acc, k| acc | (copy.next_bit() as u64) << k);
+        Lfsr::new(&mask_to_taps(lfsr.mask, lfsr.length()), reg, lfsr.length()).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Polynomial;
+
+    #[test]
+    fn mask_taps_test() {
+        assert_eq!(taps_to_mask(&[0, 1, 3, 4], 64), 0xD800_0000_0000_0000);
+        assert_eq!(taps_to_mask(&[0, 1], 4), 0b1100);
+        assert_eq!(mask_to_taps(0b1100, 4), [0, 1]);
+        assert_eq!(mask_to_taps(taps_to_mask(&[0, 2, 5, 7], 8), 8), [0, 2, 5, 7]);
+    }
+
+    #[test]
+    fn m_sequence_test() {
+        // x^16 + x^14 + x^13 + x^11 + 1, primitive
+        let mut fib = Lfsr::new(&[0, 2, 3, 5], 1, 16).unwrap();
+        let mut gal = GaloisLfsr::from(&fib);
+        assert_eq!(gal.mask(), 0xB400);
+        assert_eq!(Polynomial::from_mask(gal.mask()), fib.polynomial());
+        let start = gal.state();
+        let mut period = 0;
+        loop {
+            assert_eq!(fib.next_bit(), gal.next_bit());
+            period += 1;
+            if gal.state() == start { break; }
+        }
+        assert_eq!(period, 65535);
+    }
+
+    #[test]
+    fn conversion_test() {
+        let mut fib = Lfsr::new(&[0, 1, 3, 4], 0xABCDABCD_ABCDABCD, 64).unwrap();
+        let mut gal = GaloisLfsr::from(&fib);
+        let mut a = [0u8; 1000];
+        let mut b = [0u8; 1000];
+        fib.fill_bytes(&mut a);
+        gal.fill_bytes(&mut b);
+        assert_eq!(a, b);
+
+        let mut back = Lfsr::from(&gal);
+        assert_eq!(back.taps(), fib.taps());
+        assert_eq!(back.state(), fib.state());
+        assert_eq!(back.next_byte(), gal.next_byte());
+    }
+
+    #[test]
+    fn new_errors_test() {
+        assert_eq!(GaloisLfsr::new(0b1100, 0, 4).unwrap_err(), Error::ZeroSeed);
+        assert_eq!(GaloisLfsr::new(0b1_1000, 1, 4).unwrap_err(), Error::MaskBitOutOfRange(4));
+        assert_eq!(GaloisLfsr::new(0b1000, 1, 4).unwrap_err(), Error::TooFewTaps);
+        assert!(GaloisLfsr::new(1 << 63 | 1, 1, 64).is_ok());
+    }
+}
@@ -0,0 +1,246 @@
+//! Arithmetic on polynomials over GF(2) of degree up to 64, stored as
+//! bit masks with bit `i` being the coefficient of `x^i`.
+
+/// Degree of non-zero `p`
+pub(crate) fn degree(p: u128) -> u32 {
+    127 - p.leading_zeros()
+}
+
+/// Product of polynomials of degree below 64
+pub(crate) fn clmul(a: u64, b: u64) -> u128 {
+    (0..64).filter(|i| b >> i & 1 == 1).fold(0u128, |acc, i| acc ^ (a as u128) << i)
+}
+
+/// Remainder of `a` divided by `p`
+pub(crate) fn rem(a: u128, p: u128) -> u128 {
+    let dp = degree(p);
+    let mut a = a;
+    while a != 0 && degree(a) >= dp {
+        a ^= p << (degree(a) - dp);
+    }
+    a
+}
+
+/// Quotient of `a` divided by `p`
+pub(crate) fn div(a: u128, p: u128) -> u128 {
+    let dp = degree(p);
+    let (mut a, mut q) = (a, 0);
+    while a != 0 && degree(a) >= dp {
+        q |= 1 << (degree(a) - dp);
+        a ^= p << (degree(a) - dp);
+    }
+    q
+}
+
+/// `a * b mod p` for `a` and `b` already reduced mod `p`, degree of `p` at most 64
+pub(crate) fn mul_mod(a: u128, b: u128, p: u128) -> u128 {
+    rem(clmul(a as u64, b as u64), p)
+}
+
+/// `a^e mod p`
+pub(crate) fn pow_mod(a: u128, e: u128, p: u128) -> u128 {
+    let mut result = rem(1, p);
+    let mut base = rem(a, p);
+    let mut e = e;
+    while e != 0 {
+        if e & 1 == 1 { result = mul_mod(result, base, p); }
+        base = mul_mod(base, base, p);
+        e >>= 1;
+    }
+    result
+}
+
+/// `x^(2^k) mod p`, by squaring `k` times
+pub(crate) fn x_pow_pow2(k: u32, p: u128) -> u128 {
+    (0..k).fold(rem(0b10, p), |acc, _| mul_mod(acc, acc, p))
+}
+
+/// `a * b mod x^64`, iterating over the terms of `b`
+pub(crate) fn mul_mod_x64(a: u64, b: u64) -> u64 {
+    let (mut result, mut b) = (0, b);
+    while b != 0 {
+        result ^= a << b.trailing_zeros();
+        b &= b - 1;
+    }
+    result
+}
+
+/// Inverse of `p` modulo `x^64`, `p` having constant term 1, by Newton iteration:
+/// if `p * q = 1 + e` with `e = 0 mod x^k`, then `p * (p * q^2) = (1 + e)^2 = 1 mod x^2k`
+pub(crate) fn inverse_mod_x64(p: u64) -> u64 {
+    (0..6).fold(1, |q, _| mul_mod_x64(p, mul_mod_x64(q, q)))
+}
+
+/// Inverse of `1 + sum(x^(length - tap))` modulo `x^64`.
+///
+/// Clocking a register `count <= min(64, length)` times, new bit `j` is the sum of bits
+/// `j + tap`, which are new bits `j + tap - length` when not below `length`. So the new
+/// bits `b` satisfy `b = a + b * sum(x^(length - tap))` for `a` summed from the old bits,
+/// and `b = a * feedback_inverse(..) mod x^count`.
+pub(crate) fn feedback_inverse(taps: impl Iterator<Item = u32>, length: u32) -> u64 {
+    inverse_mod_x64(taps.filter(|&tap| length - tap < 64).fold(1, |acc, tap| acc | 1 << (length - tap)))
+}
+
+pub(crate) fn gcd(a: u128, b: u128) -> u128 {
+    let (mut a, mut b) = (a, b);
+    while b != 0 {
+        (a, b) = (b, rem(a, b));
+    }
+    a
+}
+
+/// Rabin's test: `p` of degree `n` is irreducible iff `x^(2^n) = x mod p`
+/// and `gcd(x^(2^(n/q)) - x, p) = 1` for every prime `q` dividing `n`.
+pub(crate) fn is_irreducible(p: u128) -> bool {
+    let n = degree(p);
+    if n == 0 { return false; }
+    if p & 1 == 0 { return n == 1; }
+    if x_pow_pow2(n, p) != rem(0b10, p) { return false; }
+    crate::factor::factorize(n as u64).iter()
+        .all(|&(q, _)| gcd(p, x_pow_pow2(n / q as u32, p) ^ 0b10) == 1)
+}
+
+/// Irreducible `p` of degree `n` is primitive iff `x^((2^n - 1) / r) != 1 mod p`
+/// for every prime `r` dividing `2^n - 1`.
+pub(crate) fn is_primitive(p: u128) -> bool {
+    let n = degree(p);
+    if !is_irreducible(p) { return false; }
+    if n == 1 { return p == 0b11; }
+    let order = u64::MAX >> (64 - n);
+    crate::factor::factorize(order).iter()
+        .all(|&(r, _)| pow_mod(0b10, (order / r) as u128, p) != 1)
+}
+
+/// Formal derivative, in characteristic 2 only odd powers survive
+fn derivative(p: u128) -> u128 {
+    p >> 1 & 0x5555_5555_5555_5555_5555_5555_5555_5555
+}
+
+/// Square root of a polynomial with only even powers
+fn sqrt(p: u128) -> u128 {
+    (0..64).fold(0, |acc, i| acc | (p >> (2 * i) & 1) << i)
+}
+
+/// Square-free factorization: square-free, pairwise coprime polynomials with
+/// their multiplicities, `p` is the product of their powers
+pub(crate) fn squarefree_factors(p: u128) -> Vec<(u128, u32)> {
+    let mut factors = Vec::new();
+    let mut c = gcd(p, derivative(p));
+    let mut w = div(p, c);
+    let mut i = 1;
+    while w != 1 {
+        let y = gcd(w, c);
+        let z = div(w, y);
+        if z != 1 { factors.push((z, i)); }
+        (w, c, i) = (y, div(c, y), i + 1);
+    }
+    // What is left has only multiplicities divisible by 2
+    if c != 1 {
+        factors.extend(squarefree_factors(sqrt(c)).into_iter().map(|(f, k)| (f, 2 * k)));
+    }
+    factors
+}
+
+/// Order of `x` modulo `p`, a product of distinct irreducible polynomials of degree `d`,
+/// which divides `2^d - 1`
+fn equal_degree_order(p: u128, d: u32) -> u64 {
+    let mut order = u64::MAX >> (64 - d);
+    for (r, k) in crate::factor::factorize(order) {
+        for _ in 0..k {
+            if pow_mod(0b10, (order / r) as u128, p) != 1 { break; }
+            order /= r;
+        }
+    }
+    order
+}
+
+fn lcm(a: u64, b: u64) -> u64 {
+    a / crate::factor::gcd(a, b) * b
+}
+
+/// Order of `x` modulo square-free `p`, splitting it by degrees of irreducible factors:
+/// `x^(2^d) = x` modulo exactly the factors of degree dividing `d`
+fn squarefree_order(p: u128) -> u64 {
+    let mut order = 1;
+    let mut rest = p;
+    let mut x_pow = rem(0b10, p);
+    let mut d = 0;
+    while degree(rest) > 0 {
+        d += 1;
+        if 2 * d > degree(rest) {
+            // Remaining factor is irreducible
+            return lcm(order, equal_degree_order(rest, degree(rest)));
+        }
+        x_pow = mul_mod(x_pow, x_pow, rest);
+        let factors = gcd(rest, x_pow ^ 0b10);
+        if factors != 1 {
+            order = lcm(order, equal_degree_order(factors, d));
+            rest = div(rest, factors);
+            x_pow = rem(x_pow, rest);
+        }
+    }
+    order
+}
+
+/// Smallest `e` with `p` dividing `x^e + 1`, for `p` of degree up to 64 with constant term 1.
+///
+/// For `p` being the product of irreducible `p_i^k_i`, this is the lcm of orders of all
+/// `p_i` times the smallest power of 2 not below every `k_i`.
+pub(crate) fn order(p: u128) -> u64 {
+    let factors = squarefree_factors(p);
+    let max_multiplicity = factors.iter().map(|&(_, k)| k).max().unwrap_or(1);
+    let order = factors.iter().fold(1, |acc, &(f, _)| lcm(acc, squarefree_order(f)));
+    order << max_multiplicity.next_power_of_two().trailing_zeros()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn arithmetic_test() {
+        // (x + 1)^2 = x^2 + 1
+        assert_eq!(clmul(0b11, 0b11), 0b101);
+        assert_eq!(rem(0b10011, 0b111), 1);
+        assert_eq!(gcd(0b101, 0b11), 0b11);
+        // x^15 = 1 mod x^4 + x + 1
+        assert_eq!(pow_mod(0b10, 15, 0b10011), 1);
+        assert_eq!(x_pow_pow2(4, 0b10011), 0b10);
+        assert_eq!(div(0b10101, 0b111), 0b111);
+        // 1 / (1 + x) = 1 + x + x^2 + ...
+        assert_eq!(inverse_mod_x64(0b11), u64::MAX);
+        for p in [0b11, 0b1_1011, 1 << 63 | 1 << 60 | 1, 0xDEAD_BEEF_0BAD_F00D | 1] {
+            assert_eq!(mul_mod_x64(p, inverse_mod_x64(p)), 1);
+        }
+        assert_eq!(mul_mod_x64(0b101, 0b11), 0b1111);
+    }
+
+    #[test]
+    fn order_test() {
+        // (x + 1)^3 (x^2 + x + 1)^2
+        let p = clmul(0b1111, 0b10101);
+        assert_eq!(squarefree_factors(p), [(0b11, 3), (0b111, 2)]);
+        assert_eq!(order(p), 12);
+        assert_eq!(order(0b10011), 15);
+        assert_eq!(order(0b11111), 5);
+        assert_eq!(order(0b10101), 6);
+        // (x^3 + x + 1)(x^4 + x + 1): lcm(7, 15)
+        assert_eq!(order(clmul(0b1011, 0b10011)), 105);
+        assert_eq!(order(1 << 64 | 0b11011), u64::MAX);
+        assert_eq!(order(1), 1);
+    }
+
+    #[test]
+    fn irreducible_test() {
+        assert!(is_irreducible(0b111));
+        assert!(is_irreducible(0b10011));
+        // x^4 + x^3 + x^2 + x + 1 is irreducible, but of order 5
+        assert!(is_irreducible(0b11111));
+        assert!(!is_primitive(0b11111));
+        // (x^2 + x + 1)^2
+        assert!(!is_irreducible(0b10101));
+        assert!(!is_irreducible(0b10010));
+        assert!(is_primitive(1 << 64 | 0b11011));
+        assert!(!is_primitive(1 << 64 | 0b11));
+    }
+}
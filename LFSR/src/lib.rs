@@ -0,0 +1,336 @@
+use std::io::{self, Read, Write};
+
+mod a5;
+mod anf;
+mod attack;
+mod berlekamp_massey;
+mod boolean;
+mod combiner;
+mod cycles;
+mod e0;
+mod error;
+mod factor;
+mod filter;
+mod galois;
+mod gf2;
+mod long;
+mod nfsr;
+mod nlfsr;
+mod period;
+mod polynomial;
+mod primitive;
+mod randomness;
+#[cfg(feature = "rng")]
+mod rng;
+mod seek;
+mod trivium;
+
+pub use a5::A51;
+pub use anf::Anf;
+pub use attack::{attack_geffe, correlation_attack, recover_lfsr, Correlation};
+pub use berlekamp_massey::{berlekamp_massey, bits_from_bytes, LinearComplexity};
+pub use boolean::TruthTable;
+pub use combiner::{AlternatingStepGenerator, Geffe, SelfShrinkingGenerator, ShrinkingGenerator};
+pub use cycles::{CycleStructure, MAX_CYCLE_LENGTH};
+pub use e0::E0;
+pub use error::Error;
+pub use filter::FilterGenerator;
+pub use galois::{mask_to_taps, taps_to_mask, GaloisLfsr};
+pub use long::{long_primitive_polynomial, LongLfsr};
+pub use nfsr::{state_bit, Feedback, Nfsr};
+pub use nlfsr::Nlfsr;
+pub use polynomial::Polynomial;
+pub use primitive::primitive_polynomial;
+pub use randomness::{Randomness, RandomnessReport, SIGNIFICANCE};
+pub use seek::KeystreamReader;
+pub use trivium::Trivium;
+
+/// Source of keystream bits
+pub trait Keystream {
+    /// Returns next bit of the keystream, 0 or 1
+    fn next_bit(&mut self) -> u8;
+
+    /// Returns next `count` bits, up to 64, first one in the lowest bit.
+    /// Registers override it to produce many bits at once, the byte methods build on it.
+    fn next_bits(&mut self, count: u32) -> u64 {
+        assert!(count <= 64, "can't return more than 64 bits");
+        (0..count).fold(0u64, |acc, i| acc | (self.next_bit() as u64) << i)
+    }
+
+    /// Packs next 8 bits into a byte, first bit being the most significant
+    fn next_byte(&mut self) -> u8 {
+        (self.next_bits(8) as u8).reverse_bits()
+    }
+
+    fn fill_bytes(&mut self, buf: &mut [u8]) {
+        for chunk in buf.chunks_mut(8) {
+            let bits = self.next_bits(8 * chunk.len() as u32);
+            for (i, b) in chunk.iter_mut().enumerate() {
+                *b = ((bits >> (8 * i)) as u8).reverse_bits();
+            }
+        }
+    }
+
+    /// Xors `data` with the keystream in place
+    fn apply_keystream(&mut self, data: &mut [u8]) {
+        for chunk in data.chunks_mut(8) {
+            let bits = self.next_bits(8 * chunk.len() as u32);
+            for (i, b) in chunk.iter_mut().enumerate() {
+                *b ^= ((bits >> (8 * i)) as u8).reverse_bits();
+            }
+        }
+    }
+}
+
+/// Linear register that may or may not read its output bit in the feedback
+pub trait Register: Keystream {
+    /// Whether the feedback reads tap 0. Without it the register can reach
+    /// the all zero state and never leave it.
+    fn has_tap_zero(&self) -> bool;
+}
+
+/// Checks register length and initial value common to all register kinds
+fn check_register(init: u64, length: u8) -> Result<(), Error> {
+    if length == 0 || length > 64 { return Err(Error::InvalidLength(length as u32)); }
+    if init == 0 { return Err(Error::ZeroSeed); }
+    if length < 64 && init >> length != 0 { return Err(Error::SeedTooWide); }
+    Ok(())
+}
+
+/// Linear feedback shift register in Fibonacci form, up to 64 bits long,
+/// `LongLfsr` is the same for longer registers.
+///
+/// The register outputs its lowest bit and shifts right, the new top bit
+/// is the xor of the bits at `taps`.
+///
+/// When producing bytes, the register clocks up to 64 bits at once: feedback
+/// bits depending on each other are solved for with one carry-less
+/// multiplication, so this works for any taps.
+#[derive(Clone, Debug)]
+pub struct Lfsr {
+    reg: u64,
+    taps: Vec<u8>,
+    bit_len: u8,
+    /// `gf2::feedback_inverse` of the taps, for clocking many bits at once
+    feedback_inverse: u64,
+}
+
+impl Lfsr {
+    /// Creates a register of `length` bits, initialized with `init`
+    pub fn new(taps: &[u8], init: u64, length: u8) -> Result<Self, Error> {
+        check_register(init, length)?;
+        if taps.len() <= 1 { return Err(Error::TooFewTaps); }
+        for (i, &tap) in taps.iter().enumerate() {
+            if tap >= length { return Err(Error::TapOutOfRange(tap as u32)); }
+            if taps[..i].contains(&tap) { return Err(Error::DuplicateTap(tap as u32)); }
+        }
+        Ok(Lfsr {
+            reg: init,
+            taps: taps.to_vec(),
+            bit_len: length,
+            feedback_inverse: gf2::feedback_inverse(taps.iter().map(|&tap| tap as u32), length as u32),
+        })
+    }
+
+    /// Current contents of the register
+    pub fn state(&self) -> u64 {
+        self.reg
+    }
+
+    pub fn taps(&self) -> &[u8] {
+        &self.taps
+    }
+
+    pub fn length(&self) -> u8 {
+        self.bit_len
+    }
+
+    /// Bit `i` of the register, bit 0 is the next output
+    pub fn bit(&self, i: u8) -> u8 {
+        (self.reg >> i & 1) as u8
+    }
+
+    /// Clocks the register once with `input` xored into the feedback bit and returns
+    /// the output bit. Ciphers load key material into their registers this way.
+    pub fn clock_with_input(&mut self, input: u8) -> u8 {
+        let out = self.clock(1);
+        self.reg ^= ((input & 1) as u64) << (self.bit_len - 1);
+        out as u8
+    }
+
+    /// Shifts the register `count` times, `count` up to the length, and returns the bits shifted out
+    fn clock(&mut self, count: u32) -> u64 {
+        let low = u64::MAX >> (64 - count);
+        let out = self.reg & low;
+        let from_old = self.taps.iter().fold(0u64, |acc, &tap| acc ^ self.reg >> tap) & low;
+        let feedback = gf2::mul_mod_x64(from_old, self.feedback_inverse) & low;
+        self.reg = self.reg.checked_shr(count).unwrap_or(0) | feedback << (self.bit_len as u32 - count);
+        out
+    }
+}
+
+impl Keystream for Lfsr {
+    /// Returns next bit in the register and shifts the register
+    fn next_bit(&mut self) -> u8 {
+        self.clock(1) as u8
+    }
+
+    fn next_bits(&mut self, count: u32) -> u64 {
+        assert!(count <= 64, "can't return more than 64 bits");
+        let mut bits = 0u64;
+        let mut done = 0;
+        while done < count {
+            let n = (count - done).min(self.bit_len as u32);
+            bits |= self.clock(n) << done;
+            done += n;
+        }
+        bits
+    }
+}
+
+impl Register for Lfsr {
+    fn has_tap_zero(&self) -> bool {
+        self.taps.contains(&0)
+    }
+}
+
+/// Xors everything from `input` with the keystream of `lfsr` and writes it to `out`.
+/// Encryption and decryption are the same operation.
+pub fn xor_stream<R, W, K>(input: &mut R, out: &mut W, lfsr: &mut K) -> io::Result<()>
+where
+    R: Read,
+    W: Write,
+    K: Keystream,
+{
+    let mut buf = vec![0u8; 8192];
+    loop {
+        let len = match input.read(&mut buf) {
+            Ok(0) => break,
+            Ok(len) => len,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        lfsr.apply_keystream(&mut buf[..len]);
+        out.write_all(&buf[..len])?;
+    }
+    out.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn next_bit_test() {
+        // 4 bit register with x^4 + x^3 + 1
+        let mut lfsr = Lfsr::new(&[0, 1], 0b0001, 4).unwrap();
+        let bits: Vec<u8> = (0..15).map(|_| lfsr.next_bit()).collect();
+        assert_eq!(bits, [1, 0, 0, 0, 1, 0, 0, 1, 1, 0, 1, 0, 1, 1, 1]);
+        assert_eq!(lfsr.state(), 0b0001);
+    }
+
+    #[test]
+    fn period_test() {
+        let mut lfsr = Lfsr::new(&[0, 2], 1, 5).unwrap();
+        let mut period = 0;
+        loop {
+            lfsr.next_bit();
+            period += 1;
+            if lfsr.state() == 1 { break; }
+        }
+        assert_eq!(period, 31);
+    }
+
+    #[test]
+    fn next_byte_test() {
+        let mut bits = Lfsr::new(&[0, 1, 3, 4], 0xABCDABCD_ABCDABCD, 64).unwrap();
+        let mut bytes = bits.clone();
+        for _ in 0..100 {
+            let expected = (0..8).fold(0u8, |acc, _| (acc << 1) | bits.next_bit());
+            assert_eq!(bytes.next_byte(), expected);
+        }
+    }
+
+    #[test]
+    fn fill_bytes_test() {
+        let mut lfsr = Lfsr::new(&[0, 1, 3, 4], 0xABCDABCD_ABCDABCD, 64).unwrap();
+        let mut copy = lfsr.clone();
+        let mut buf = [0u8; 33];
+        lfsr.fill_bytes(&mut buf);
+        assert!(buf.iter().all(|b| *b == copy.next_byte()));
+        assert_eq!(&buf[..4], [0xB3, 0xD5, 0xB3, 0xD5]);
+    }
+
+    #[test]
+    fn word_path_test() {
+        // Reference bit by bit register, as the feedback is defined
+        fn reference(taps: &[u8], init: u64, length: u8, count: usize) -> Vec<u8> {
+            let mut reg = init;
+            (0..count).map(|_| {
+                let out = (reg & 1) as u8;
+                let bit = taps.iter().fold(0, |acc, &t| acc ^ (reg >> t) & 1);
+                reg = reg >> 1 | bit << (length - 1);
+                out
+            }).collect()
+        }
+
+        let configs: [(&[u8], u64, u8); 7] = [
+            (&[0, 1, 3, 4], 0xABCDABCD_ABCDABCD, 64),
+            (&[0, 63], 0x8000_0000_0000_0001, 64),
+            // primitive_polynomial(64), taps next to the top
+            (&[0, 60, 61, 63], 0xDEAD_BEEF_0BAD_F00D, 64),
+            (&[0, 2, 3, 4], 0b11001, 5),
+            (&[1, 2], 0b10111, 5),
+            (&[0, 5, 17, 30], 0x1234_5678, 31),
+            (&[0, 2, 3, 5], 0xACE1, 16),
+        ];
+        for (taps, init, length) in configs {
+            let expected = reference(taps, init, length, 8 * 1001);
+            let lfsr = Lfsr::new(taps, init, length).unwrap();
+
+            let mut bits = lfsr.clone();
+            assert!(expected.iter().all(|&b| b == bits.next_bit()));
+
+            let mut bytes = vec![0u8; 1001];
+            lfsr.clone().fill_bytes(&mut bytes);
+            assert_eq!(bits_from_bytes(&bytes), expected, "taps {:?}", taps);
+
+            let mut words = lfsr.clone();
+            let mut pos = 0;
+            for count in [1, 7, 64, 33, 8, 63, 32] {
+                let word = words.next_bits(count);
+                for i in 0..count as usize {
+                    assert_eq!((word >> i & 1) as u8, expected[pos + i]);
+                }
+                pos += count as usize;
+            }
+        }
+    }
+
+    #[test]
+    fn xor_stream_test() {
+        let plain: Vec<u8> = (0..20000u32).map(|i| (i * 31) as u8).collect();
+        let mut encrypted = Vec::new();
+        let mut decrypted = Vec::new();
+        xor_stream(&mut plain.as_slice(), &mut encrypted, &mut Lfsr::new(&[0, 1, 3, 4], 0x1234, 64).unwrap()).unwrap();
+        assert_ne!(plain, encrypted);
+        xor_stream(&mut encrypted.as_slice(), &mut decrypted, &mut Lfsr::new(&[0, 1, 3, 4], 0x1234, 64).unwrap()).unwrap();
+        assert_eq!(plain, decrypted);
+    }
+
+    #[test]
+    fn new_errors_test() {
+        assert_eq!(Lfsr::new(&[0, 1], 1, 0).unwrap_err(), Error::InvalidLength(0));
+        assert_eq!(Lfsr::new(&[0, 1], 1, 65).unwrap_err(), Error::InvalidLength(65));
+        assert_eq!(Lfsr::new(&[0, 1], 0, 8).unwrap_err(), Error::ZeroSeed);
+        assert_eq!(Lfsr::new(&[0, 1], 0x100, 8).unwrap_err(), Error::SeedTooWide);
+        assert_eq!(Lfsr::new(&[0, 8], 1, 8).unwrap_err(), Error::TapOutOfRange(8));
+        assert_eq!(Lfsr::new(&[0, 70], 1, 64).unwrap_err(), Error::TapOutOfRange(70));
+        assert_eq!(Lfsr::new(&[0, 3, 3], 1, 8).unwrap_err(), Error::DuplicateTap(3));
+        assert_eq!(Lfsr::new(&[0], 1, 8).unwrap_err(), Error::TooFewTaps);
+        assert_eq!(Lfsr::new(&[], 1, 8).unwrap_err(), Error::TooFewTaps);
+
+        assert!(Lfsr::new(&[0, 63], u64::MAX, 64).is_ok());
+        assert!(Lfsr::new(&[0, 7], 0xFF, 8).is_ok());
+    }
+}
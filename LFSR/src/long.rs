@@ -0,0 +1,319 @@
+use crate::berlekamp_massey::word_at;
+use crate::{gf2, primitive_polynomial, Error, Keystream, Lfsr, Polynomial, Register};
+
+/// Primitive trinomials `x^n + x^k + 1` for `n` with prime `2^n - 1`
+const MERSENNE_TRINOMIALS: [(u32, u32); 6] = [(89, 38), (127, 1), (521, 32), (607, 105), (1279, 216), (2281, 715)];
+
+/// Known primitive polynomial of degree `length`, for lengths 2 to 64
+/// and Mersenne exponents 89, 127, 521, 607, 1279 and 2281
+pub fn long_primitive_polynomial(length: u32) -> Option<Polynomial> {
+    if let Ok(length) = u8::try_from(length) {
+        if let Some(poly) = primitive_polynomial(length) { return Some(poly); }
+    }
+    let &(n, k) = MERSENNE_TRINOMIALS.iter().find(|&&(n, _)| n == length)?;
+    Some(Polynomial::from_terms(&[n, k]).unwrap())
+}
+
+/// Checks length and initial words of a register stored in 64 bit words
+pub(crate) fn check_words(init: &[u64], length: u32) -> Result<(), Error> {
+    if length == 0 { return Err(Error::InvalidLength(length)); }
+    // Highest set bit of word `i` is at `64 * i + 63 - leading_zeros`
+    if init.iter().enumerate().any(|(i, &w)| w != 0 && 64 * i as u32 + 64 - w.leading_zeros() > length) {
+        return Err(Error::SeedTooWide);
+    }
+    Ok(())
+}
+
+/// Linear feedback shift register in Fibonacci form of any length.
+///
+/// Works the same as `Lfsr`, the state is stored in 64 bit words,
+/// bit `i` of the register at `state()[i / 64] >> (i % 64)`. It has the methods of `Lfsr`
+/// except those needing the factorization of `2^length - 1` (`period` and
+/// `with_primitive_polynomial`) or the state in one word (`cycle_structure` and `with_filter`).
+#[derive(Clone, Debug)]
+pub struct LongLfsr {
+    reg: Vec<u64>,
+    taps: Vec<u32>,
+    bit_len: u32,
+    /// `gf2::feedback_inverse` of the taps, for clocking many bits at once
+    feedback_inverse: u64,
+}
+
+impl LongLfsr {
+    /// Creates a register of `length` bits, initialized with `init` words, lowest first
+    pub fn new(taps: &[u32], init: &[u64], length: u32) -> Result<Self, Error> {
+        check_words(init, length)?;
+        if init.iter().all(|&w| w == 0) { return Err(Error::ZeroSeed); }
+        if taps.len() <= 1 { return Err(Error::TooFewTaps); }
+        for (i, &tap) in taps.iter().enumerate() {
+            if tap >= length { return Err(Error::TapOutOfRange(tap)); }
+            if taps[..i].contains(&tap) { return Err(Error::DuplicateTap(tap)); }
+        }
+        let mut reg = init.to_vec();
+        reg.resize(length.div_ceil(64) as usize, 0);
+        Ok(LongLfsr {
+            reg,
+            taps: taps.to_vec(),
+            bit_len: length,
+            feedback_inverse: gf2::feedback_inverse(taps.iter().copied(), length),
+        })
+    }
+
+    /// Creates a register with feedback polynomial `poly`, initialized with `init`
+    pub fn with_polynomial(poly: &Polynomial, init: &[u64]) -> Result<Self, Error> {
+        let length = poly.degree();
+        let taps: Vec<u32> = poly.terms()[..poly.terms().len() - 1].iter().map(|&e| length - e).collect();
+        LongLfsr::new(&taps, init, length)
+    }
+
+    /// Current contents of the register
+    pub fn state(&self) -> &[u64] {
+        &self.reg
+    }
+
+    pub fn taps(&self) -> &[u32] {
+        &self.taps
+    }
+
+    pub fn length(&self) -> u32 {
+        self.bit_len
+    }
+
+    /// Feedback polynomial, an error for registers without tap 0 like `Lfsr::polynomial`
+    pub fn polynomial(&self) -> Result<Polynomial, Error> {
+        if !self.taps.contains(&0) {
+            return Err(Error::MissingTapZero);
+        }
+        Polynomial::from_terms(&self.taps.iter().map(|&tap| self.bit_len - tap).collect::<Vec<_>>())
+    }
+
+    /// Bit `i` of the register, bit 0 is the next output
+    pub fn bit(&self, i: u32) -> u8 {
+        (self.reg[i as usize / 64] >> (i % 64) & 1) as u8
+    }
+
+    /// Clocks the register once with `input` xored into the feedback bit and returns
+    /// the output bit, like `Lfsr::clock_with_input`
+    pub fn clock_with_input(&mut self, input: u8) -> u8 {
+        let out = self.clock(1);
+        let top = self.bit_len - 1;
+        self.reg[top as usize / 64] ^= ((input & 1) as u64) << (top % 64);
+        out as u8
+    }
+
+    /// Moves the register `n` steps forward like `Lfsr::advance`, with polynomials
+    /// modulo the characteristic polynomial stored in 64 bit words
+    pub fn advance(&mut self, n: u128) {
+        let len = self.bit_len as usize;
+        let mut low = vec![0u64; self.reg.len()];
+        for &tap in &self.taps {
+            low[tap as usize / 64] ^= 1 << (tap % 64);
+        }
+        // Multiplies by x, then x^length is replaced by the taps
+        let times_x = |p: &mut Vec<u64>| {
+            let top = p[(len - 1) / 64] >> ((len - 1) % 64) & 1;
+            for i in (0..p.len()).rev() {
+                p[i] = p[i] << 1 | if i > 0 { p[i - 1] >> 63 } else { 0 };
+            }
+            *p.last_mut().unwrap() &= u64::MAX >> (64 * p.len() - len);
+            if top == 1 {
+                p.iter_mut().zip(&low).for_each(|(w, l)| *w ^= l);
+            }
+        };
+        let mul_mod = |a: &Vec<u64>, b: &Vec<u64>| {
+            let mut product = vec![0u64; a.len()];
+            for i in (0..len).rev() {
+                times_x(&mut product);
+                if b[i / 64] >> (i % 64) & 1 == 1 {
+                    product.iter_mut().zip(a).for_each(|(w, a)| *w ^= a);
+                }
+            }
+            product
+        };
+        let mut power = vec![0u64; self.reg.len()];
+        power[0] = 1;
+        for i in (0..u128::BITS - n.leading_zeros()).rev() {
+            power = mul_mod(&power, &power);
+            if n >> i & 1 == 1 { times_x(&mut power); }
+        }
+        // Same as in `Lfsr::advance`, bit i of the new state is x^(n+i) applied to the current one
+        let mut reg = vec![0u64; self.reg.len()];
+        for i in 0..len {
+            let bit = power.iter().zip(&self.reg).fold(0, |acc, (p, r)| acc ^ (p & r).count_ones()) & 1;
+            reg[i / 64] |= (bit as u64) << (i % 64);
+            times_x(&mut power);
+        }
+        self.reg = reg;
+    }
+
+    /// Shifts the register `count` times, `count` up to 64 and the length, and returns the bits
+    /// shifted out. Works the same as in `Lfsr`.
+    fn clock(&mut self, count: u32) -> u64 {
+        let low = u64::MAX >> (64 - count);
+        let out = self.reg[0] & low;
+        let from_old = self.taps.iter().fold(0u64, |acc, &tap| acc ^ word_at(&self.reg, tap as usize)) & low;
+        let feedback = gf2::mul_mod_x64(from_old, self.feedback_inverse) & low;
+        // Word `i` only reads words `i` and `i + 1`, so shifting in place is fine
+        for i in 0..self.reg.len() {
+            self.reg[i] = word_at(&self.reg, 64 * i + count as usize);
+        }
+        let pos = (self.bit_len - count) as usize;
+        let (word, shift) = (pos / 64, pos % 64);
+        self.reg[word] |= feedback << shift;
+        if shift + count as usize > 64 {
+            self.reg[word + 1] |= feedback >> (64 - shift);
+        }
+        out
+    }
+}
+
+impl Keystream for LongLfsr {
+    fn next_bit(&mut self) -> u8 {
+        self.clock(1) as u8
+    }
+
+    fn next_bits(&mut self, count: u32) -> u64 {
+        assert!(count <= 64, "can't return more than 64 bits");
+        let mut bits = 0u64;
+        let mut done = 0;
+        while done < count {
+            let n = (count - done).min(self.bit_len);
+            bits |= self.clock(n) << done;
+            done += n;
+        }
+        bits
+    }
+}
+
+impl Register for LongLfsr {
+    fn has_tap_zero(&self) -> bool {
+        self.taps.contains(&0)
+    }
+}
+
+impl From<&Lfsr> for LongLfsr {
+    /// Register that continues the output of `lfsr`
+    fn from(lfsr: &Lfsr) -> Self {
+        let taps: Vec<u32> = lfsr.taps().iter().map(|&tap| tap as u32).collect();
+        LongLfsr::new(&taps, &[lfsr.state()], lfsr.length() as u32).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Output of the register computed from the recurrence `s[j+length] = sum(s[j+tap])`
+    fn reference(taps: &[u32], init: &[u64], length: u32, count: usize) -> Vec<u8> {
+        let mut seq: Vec<u8> = (0..length as usize).map(|i| (init[i / 64] >> (i % 64) & 1) as u8).collect();
+        for j in 0..count {
+            let bit = taps.iter().fold(0, |acc, &t| acc ^ seq[j + t as usize]);
+            seq.push(bit);
+        }
+        seq.truncate(count);
+        seq
+    }
+
+    #[test]
+    fn same_as_lfsr_test() {
+        for (taps, init, length) in [(&[0u8, 1, 3, 4][..], 0xABCDABCD_ABCDABCD, 64), (&[0, 5, 17, 30], 0x1234_5678, 31)] {
+            let mut lfsr = Lfsr::new(taps, init, length).unwrap();
+            let mut long = LongLfsr::from(&lfsr);
+            let (mut a, mut b) = ([0u8; 999], [0u8; 999]);
+            lfsr.fill_bytes(&mut a);
+            long.fill_bytes(&mut b);
+            assert_eq!(a, b);
+            assert_eq!(long.state(), [lfsr.state()]);
+            assert_eq!(long.polynomial(), lfsr.polynomial());
+        }
+        let no_tap0 = LongLfsr::from(&Lfsr::new(&[1, 2], 0b1000, 4).unwrap());
+        assert_eq!(no_tap0.polynomial(), Err(Error::MissingTapZero));
+    }
+
+    #[test]
+    fn long_registers_test() {
+        let init = [0x0123_4567_89AB_CDEF, 0xFEDC_BA98_7654_3210, 0x5555_AAAA_5555_AAAA, 1, 0, 0, 0, 0, 0x1F];
+        for length in [89, 127, 521] {
+            let poly = long_primitive_polynomial(length).unwrap();
+            let mut words = init[..length.div_ceil(64) as usize].to_vec();
+            *words.last_mut().unwrap() &= u64::MAX >> (64 * words.len() as u32 - length);
+            let mut lfsr = LongLfsr::with_polynomial(&poly, &words).unwrap();
+            let expected = reference(lfsr.taps(), &words, length, 5000);
+            assert!(expected.iter().all(|&b| b == lfsr.next_bit()), "{}", poly);
+        }
+
+        // One tap at the very top, every new bit depends on the previous one
+        let mut lfsr = LongLfsr::new(&[0, 99], &[3, 1 << 35], 100).unwrap();
+        let expected = reference(&[0, 99], &[3, 1 << 35], 100, 8 * 500);
+        let mut bytes = [0u8; 500];
+        lfsr.fill_bytes(&mut bytes);
+        assert_eq!(crate::bits_from_bytes(&bytes), expected);
+    }
+
+    #[test]
+    fn advance_test() {
+        for (init, length) in [(&[0x0123_4567_89AB_CDEF, 0x1234][..], 127), (&[5, 0, 0, 0, 0, 0, 0, 0, 1 << 8][..], 521)] {
+            let poly = long_primitive_polynomial(length).unwrap();
+            let mut stepped = LongLfsr::with_polynomial(&poly, init).unwrap();
+            let mut jumped = stepped.clone();
+            for n in [0, 1, 63, 1000, 4321] {
+                for _ in 0..n { stepped.next_bit(); }
+                jumped.advance(n);
+                assert_eq!(jumped.state(), stepped.state(), "{} {}", length, n);
+            }
+        }
+        // Same jump as a 64 bit register, including a register without tap 0
+        for taps in [&[0u8, 1, 3, 4][..], &[2, 9, 40]] {
+            let mut lfsr = Lfsr::new(taps, 0xDEAD_BEEF_0BAD_F00D, 64).unwrap();
+            let mut long = LongLfsr::from(&lfsr);
+            lfsr.advance(1 << 100);
+            long.advance(1 << 100);
+            assert_eq!(long.state(), [lfsr.state()]);
+        }
+    }
+
+    #[test]
+    fn bit_test() {
+        let lfsr = Lfsr::new(&[0, 5, 17, 30], 0x1234_5678, 31).unwrap();
+        let mut long = LongLfsr::from(&lfsr);
+        assert!((0..31).all(|i| long.bit(i) == lfsr.bit(i as u8)));
+
+        let mut short = lfsr.clone();
+        for input in [1, 0, 1, 1, 0, 0, 1] {
+            assert_eq!(long.clock_with_input(input), short.clock_with_input(input));
+        }
+        assert_eq!(long.state(), [short.state()]);
+        let mut long = LongLfsr::new(&[0, 126], &[1, 0], 127).unwrap();
+        long.clock_with_input(1);
+        assert_eq!((long.bit(126), long.state()), (0, &[0, 0][..]));
+    }
+
+    #[test]
+    fn period_test() {
+        for n in 2..=14 {
+            let mut lfsr = LongLfsr::with_polynomial(&long_primitive_polynomial(n).unwrap(), &[1]).unwrap();
+            let mut period = 0u64;
+            loop {
+                lfsr.next_bit();
+                period += 1;
+                if lfsr.state() == [1] { break; }
+            }
+            assert_eq!(period, (1 << n) - 1);
+        }
+    }
+
+    #[test]
+    fn new_errors_test() {
+        assert_eq!(LongLfsr::new(&[0, 1], &[1], 0).unwrap_err(), Error::InvalidLength(0));
+        assert_eq!(LongLfsr::new(&[0, 1], &[0, 0], 127).unwrap_err(), Error::ZeroSeed);
+        assert_eq!(LongLfsr::new(&[0, 1], &[1, 1 << 63], 127).unwrap_err(), Error::SeedTooWide);
+        assert_eq!(LongLfsr::new(&[0, 1], &[1, 0, 1], 128).unwrap_err(), Error::SeedTooWide);
+        assert_eq!(LongLfsr::new(&[0, 127], &[1], 127).unwrap_err(), Error::TapOutOfRange(127));
+        assert_eq!(LongLfsr::new(&[0, 3, 3], &[1], 127).unwrap_err(), Error::DuplicateTap(3));
+        assert_eq!(LongLfsr::new(&[0], &[1], 127).unwrap_err(), Error::TooFewTaps);
+        assert!(LongLfsr::new(&[0, 126], &[1, 1 << 62], 127).is_ok());
+        assert!(LongLfsr::new(&[0, 127], &[0, u64::MAX], 128).is_ok());
+        assert!(long_primitive_polynomial(65).is_none());
+        assert_eq!(long_primitive_polynomial(521).unwrap().to_string(), "x^521 + x^32 + 1");
+    }
+}
@@ -0,0 +1,787 @@
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::fs::File;
+use std::env;
+use std::process::ExitCode;
+
+use lfsr::{berlekamp_massey, bits_from_bytes, attack_geffe, correlation_attack, primitive_polynomial, recover_lfsr, xor_stream, AlternatingStepGenerator, Anf, Error, Geffe, Keystream, Lfsr, Nlfsr, Polynomial, Randomness, SelfShrinkingGenerator, ShrinkingGenerator, A51, MAX_CYCLE_LENGTH};
+
+const USAGE: &str = "\
+Usage: lfsr COMMAND [OPTIONS] [ARGS]
+
+Commands:
+  encrypt [INPUT] [OUTPUT]              Xor INPUT with the register keystream into OUTPUT
+  combine KIND [INPUT] [OUTPUT]         Xor INPUT with the keystream of a combination generator,
+                                        KIND is geffe, shrinking, self-shrinking or alternating-step
+  keystream BYTES [OUTPUT]              Write BYTES bytes of the register keystream
+  analyze                               Order of the feedback polynomial, period from --seed
+                                        and cycles of registers up to 24 bits
+  complexity [INPUT]                    Shortest register generating INPUT
+  randomness [INPUT]                    NIST SP 800-22 statistical tests on INPUT
+  recover CIPHER KNOWN [OUTPUT]         Recover the register from CIPHER and its known
+                                        plaintext prefix KNOWN, then decrypt CIPHER
+  attack correlation INPUT POLY...      Correlation attack on a combiner keystream,
+                                        one feedback polynomial per register
+  attack geffe INPUT POLY1 POLY2 POLY3  Recover seeds of the three registers of a Geffe
+                                        generator, POLY2 being the selector
+  a51 KEY FRAME [INPUT] [OUTPUT]        Xor INPUT with A5/1 keystream for 64 bit KEY
+                                        in hex and 22 bit FRAME number
+  nlfsr ANF LENGTH                      Cycles of a nonlinear register with feedback ANF
+  nlfsr ANF LENGTH SEED BYTES [OUTPUT]  Write BYTES bytes of its keystream
+
+A missing path or \"-\" means stdin or stdout.
+
+Register options for encrypt, keystream and analyze:
+  -t, --taps TAPS        Comma separated taps, bit 0 being the output
+  -p, --poly POLY        Feedback polynomial like \"x^4 + x + 1\" or its hex mask
+  -l, --length N         Register length in bits [default: degree of POLY or 64]
+  -s, --seed HEX         Initial state in hex, required for encrypt and keystream
+      --bit-order ORDER  Packing of keystream bits into bytes: msb, lsb [default: msb]
+  -h, --help             Print this message
+
+Without --taps and --poly, a primitive polynomial of the given length is used.
+
+combine takes --poly and --seed once per register, in order, and --bit-order:
+  geffe             x1, selector, x3
+  shrinking         data, selector
+  self-shrinking    a single register
+  alternating-step  control, x1, x2";
+
+/// Order of keystream bits within each byte
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+enum BitOrder {
+    /// First bit is the most significant, as `Keystream` packs them
+    #[default]
+    Msb,
+    Lsb,
+}
+
+/// Generator of the combine command
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Combiner {
+    Geffe,
+    Shrinking,
+    SelfShrinking,
+    AlternatingStep,
+}
+
+impl Combiner {
+    /// Number of registers it combines
+    fn registers(self) -> usize {
+        match self {
+            Combiner::Geffe | Combiner::AlternatingStep => 3,
+            Combiner::Shrinking => 2,
+            Combiner::SelfShrinking => 1,
+        }
+    }
+}
+
+/// Register given by the options
+#[derive(Debug, Default, PartialEq)]
+struct RegisterOptions {
+    taps: Option<Vec<u8>>,
+    poly: Option<Polynomial>,
+    length: Option<u8>,
+    seed: Option<u64>,
+    bit_order: BitOrder,
+}
+
+impl RegisterOptions {
+    /// Register initialized with `seed`, from taps, polynomial or the primitive polynomial of the length
+    fn register(&self, seed: u64) -> Result<Lfsr, String> {
+        let length = self.length.unwrap_or(64);
+        match (&self.taps, &self.poly) {
+            (Some(taps), _) => Lfsr::new(taps, seed, length),
+            (None, Some(poly)) => Lfsr::with_polynomial(poly, seed),
+            (None, None) => {
+                let poly = primitive_polynomial(length)
+                    .ok_or_else(|| format!("no primitive polynomial of length {} is known", length))?;
+                Lfsr::with_polynomial(&poly, seed)
+            }
+        }.map_err(|e| format!("incorrect register: {}", e))
+    }
+}
+
+/// Result of parsing the command line
+#[derive(Debug, PartialEq)]
+enum Command {
+    Help,
+    Encrypt { register: RegisterOptions, input: String, output: String },
+    Combine { combiner: Combiner, registers: Vec<(Polynomial, u64)>, bit_order: BitOrder, input: String, output: String },
+    Keystream { register: RegisterOptions, bytes: u64, output: String },
+    Analyze(RegisterOptions),
+    Complexity(String),
+    Randomness(String),
+    Recover { cipher: String, known: String, output: String },
+    AttackCorrelation { input: String, polys: Vec<Polynomial> },
+    AttackGeffe { input: String, polys: [Polynomial; 3] },
+    A51 { key: u64, frame: u32, input: String, output: String },
+    NlfsrCycles { feedback: Anf, length: u8 },
+    NlfsrKeystream { feedback: Anf, length: u8, seed: u64, bytes: u64, output: String },
+}
+
+fn parse_args<I: IntoIterator<Item = String>>(args: I) -> Result<Command, String> {
+    let mut args = args.into_iter();
+    let name = args.next().ok_or("missing command")?;
+    if name == "-h" || name == "--help" {
+        return Ok(Command::Help);
+    }
+    let mut register = RegisterOptions::default();
+    let (mut polys, mut seeds) = (Vec::new(), Vec::new());
+    let mut has_options = false;
+    let mut positional = Vec::new();
+
+    while let Some(arg) = args.next() {
+        has_options |= arg.starts_with('-') && arg != "-";
+        let mut value = || args.next().ok_or_else(|| format!("missing value for {}", arg));
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(Command::Help),
+            "-t" | "--taps" => register.taps = Some(parse_taps(&value()?)?),
+            "-p" | "--poly" => polys.push(parse_poly(&value()?)?),
+            "-l" | "--length" => register.length = Some(parse_length(&value()?)?),
+            "-s" | "--seed" => seeds.push(parse_hex(&value()?, "seed")?),
+            "--bit-order" => register.bit_order = match value()?.as_str() {
+                "msb" => BitOrder::Msb,
+                "lsb" => BitOrder::Lsb,
+                other => return Err(format!("unknown bit order '{}'", other)),
+            },
+            "-" => positional.push(arg),
+            _ if arg.starts_with('-') => return Err(format!("unknown option '{}'", arg)),
+            _ => positional.push(arg),
+        }
+    }
+
+    if has_options && !matches!(name.as_str(), "encrypt" | "keystream" | "analyze" | "combine") {
+        return Err(format!("{} takes no options", name));
+    }
+    if name == "combine" {
+        return parse_combine(register, polys, seeds, &positional);
+    }
+    if polys.len() > 1 || seeds.len() > 1 {
+        return Err("--poly and --seed can only be given more than once for combine".to_owned());
+    }
+    (register.poly, register.seed) = (polys.pop(), seeds.pop());
+    if register.taps.is_some() && register.poly.is_some() {
+        return Err("--taps and --poly can't be used together".to_owned());
+    }
+    if let (Some(poly), Some(length)) = (&register.poly, register.length) {
+        if poly.degree() != length as u32 {
+            return Err(format!("--length {} doesn't match the degree of {}", length, poly));
+        }
+    }
+    if matches!(name.as_str(), "encrypt" | "keystream") && register.seed.is_none() {
+        return Err(format!("--seed is required for {}", name));
+    }
+
+    let path = |p: Option<&String>| p.cloned().unwrap_or_else(|| "-".to_owned());
+    Ok(match (name.as_str(), positional.as_slice()) {
+        ("encrypt", paths) if paths.len() <= 2 =>
+            Command::Encrypt { register, input: path(paths.first()), output: path(paths.get(1)) },
+        ("keystream", [bytes, paths @ ..]) if paths.len() <= 1 =>
+            Command::Keystream { register, bytes: parse_count(bytes)?, output: path(paths.first()) },
+        ("analyze", []) => Command::Analyze(register),
+        ("complexity", paths) if paths.len() <= 1 => Command::Complexity(path(paths.first())),
+        ("randomness", paths) if paths.len() <= 1 => Command::Randomness(path(paths.first())),
+        ("recover", [cipher, known, paths @ ..]) if paths.len() <= 1 =>
+            Command::Recover { cipher: cipher.clone(), known: known.clone(), output: path(paths.first()) },
+        ("attack", [kind, input, polys @ ..]) if kind == "correlation" && !polys.is_empty() => Command::AttackCorrelation {
+            input: input.clone(),
+            polys: polys.iter().map(|p| parse_poly(p)).collect::<Result<_, _>>()?,
+        },
+        ("attack", [kind, input, x1, x2, x3]) if kind == "geffe" => Command::AttackGeffe {
+            input: input.clone(),
+            polys: [parse_poly(x1)?, parse_poly(x2)?, parse_poly(x3)?],
+        },
+        ("a51", [key, frame, paths @ ..]) if paths.len() <= 2 => Command::A51 {
+            key: parse_hex(key, "key")?,
+            frame: frame.parse::<u32>().ok().filter(|f| f >> 22 == 0).ok_or("frame number should fit in 22 bits")?,
+            input: path(paths.first()),
+            output: path(paths.get(1)),
+        },
+        ("nlfsr", [feedback, length]) =>
+            Command::NlfsrCycles { feedback: parse_anf(feedback)?, length: parse_length(length)? },
+        ("nlfsr", [feedback, length, seed, bytes, paths @ ..]) if paths.len() <= 1 => Command::NlfsrKeystream {
+            feedback: parse_anf(feedback)?,
+            length: parse_length(length)?,
+            seed: parse_hex(seed, "seed")?,
+            bytes: parse_count(bytes)?,
+            output: path(paths.first()),
+        },
+        ("encrypt" | "keystream" | "analyze" | "complexity" | "randomness" | "recover" | "attack" | "a51" | "nlfsr", _) =>
+            return Err(format!("wrong arguments for {}", name)),
+        _ => return Err(format!("unknown command '{}'", name)),
+    })
+}
+
+/// Combine command from the parsed options, `register` holding those other than --poly and --seed
+fn parse_combine(register: RegisterOptions, polys: Vec<Polynomial>, seeds: Vec<u64>, positional: &[String]) -> Result<Command, String> {
+    if register.taps.is_some() || register.length.is_some() {
+        return Err("combine takes registers from --poly and --seed only".to_owned());
+    }
+    let (kind, paths) = positional.split_first().ok_or("missing generator for combine")?;
+    if paths.len() > 2 {
+        return Err("wrong arguments for combine".to_owned());
+    }
+    let combiner = match kind.as_str() {
+        "geffe" => Combiner::Geffe,
+        "shrinking" => Combiner::Shrinking,
+        "self-shrinking" => Combiner::SelfShrinking,
+        "alternating-step" => Combiner::AlternatingStep,
+        other => return Err(format!("unknown generator '{}'", other)),
+    };
+    if polys.len() != combiner.registers() || seeds.len() != combiner.registers() {
+        return Err(format!("{} combines {} register(s), give --poly and --seed for each", kind, combiner.registers()));
+    }
+    let path = |p: Option<&String>| p.cloned().unwrap_or_else(|| "-".to_owned());
+    Ok(Command::Combine {
+        combiner,
+        registers: polys.into_iter().zip(seeds).collect(),
+        bit_order: register.bit_order,
+        input: path(paths.first()),
+        output: path(paths.get(1)),
+    })
+}
+
+fn parse_taps(taps: &str) -> Result<Vec<u8>, String> {
+    taps.split(',')
+        .map(|tap| tap.trim().parse().map_err(|_| format!("tap '{}' is incorrect", tap)))
+        .collect()
+}
+
+fn parse_poly(poly: &str) -> Result<Polynomial, String> {
+    poly.parse().map_err(|e: Error| e.to_string())
+}
+
+fn parse_anf(feedback: &str) -> Result<Anf, String> {
+    feedback.parse().map_err(|e: Error| e.to_string())
+}
+
+fn parse_length(length: &str) -> Result<u8, String> {
+    length.parse().ok().filter(|l| (1..=64).contains(l)).ok_or_else(|| "length should be 1 to 64".to_owned())
+}
+
+fn parse_hex(hex: &str, what: &str) -> Result<u64, String> {
+    u64::from_str_radix(hex, 16).map_err(|_| format!("{} should be 1 to 16 hex digits", what))
+}
+
+fn parse_count(bytes: &str) -> Result<u64, String> {
+    bytes.parse().map_err(|_| format!("'{}' is not a number of bytes", bytes))
+}
+
+/// Keystream with bits packed into bytes least significant first
+struct LsbFirst<K>(K);
+
+impl<K: Keystream> Keystream for LsbFirst<K> {
+    fn next_bit(&mut self) -> u8 {
+        self.0.next_bit()
+    }
+
+    fn next_byte(&mut self) -> u8 {
+        self.0.next_byte().reverse_bits()
+    }
+
+    fn fill_bytes(&mut self, buf: &mut [u8]) {
+        self.0.fill_bytes(buf);
+        buf.iter_mut().for_each(|b| *b = b.reverse_bits());
+    }
+
+    fn apply_keystream(&mut self, data: &mut [u8]) {
+        data.iter_mut().for_each(|b| *b = b.reverse_bits());
+        self.0.apply_keystream(data);
+        data.iter_mut().for_each(|b| *b = b.reverse_bits());
+    }
+}
+
+fn open_input(path: &str) -> Result<Box<dyn Read>, String> {
+    if path == "-" { return Ok(Box::new(io::stdin().lock())); }
+    Ok(Box::new(BufReader::new(File::open(path).map_err(|e| format!("can't open {}: {}", path, e))?)))
+}
+
+fn create_output(path: &str) -> Result<Box<dyn Write>, String> {
+    if path == "-" { return Ok(Box::new(io::stdout().lock())); }
+    Ok(Box::new(BufWriter::new(File::create(path).map_err(|e| format!("can't create {}: {}", path, e))?)))
+}
+
+fn read_all(path: &str) -> Result<Vec<u8>, String> {
+    let mut data = Vec::new();
+    open_input(path)?.read_to_end(&mut data).map_err(|e| format!("can't read {}: {}", path, e))?;
+    Ok(data)
+}
+
+/// Writes `bytes` bytes of keystream
+fn write_keystream<K: Keystream>(keystream: &mut K, bytes: u64, output: &mut dyn Write) -> io::Result<()> {
+    let mut buf = vec![0u8; 8192];
+    let mut left = bytes;
+    while left > 0 {
+        let len = left.min(buf.len() as u64) as usize;
+        keystream.fill_bytes(&mut buf[..len]);
+        output.write_all(&buf[..len])?;
+        left -= len as u64;
+    }
+    output.flush()
+}
+
+/// Warning for a register whose keystream from its seed repeats before 2^length-1 bits
+fn period_warning(reg: &Lfsr) -> Option<String> {
+    let maximal = u64::MAX >> (64 - reg.length());
+    let period = reg.period();
+    if period == maximal {
+        return None;
+    }
+    let mut warning = format!("Warning: keystream period is {}, shorter than 2^{}-1", period, reg.length());
+    match reg.polynomial() {
+        Ok(poly) => warning += &format!(", feedback polynomial {} is not primitive", poly),
+        Err(_) => warning += ", register has no tap 0",
+    }
+    if let Some(poly) = primitive_polynomial(reg.length()) {
+        warning += &format!("\nPrimitive polynomial of this length is {}", poly);
+    }
+    Some(warning)
+}
+
+/// Register for encrypt and keystream, warns when its period isn't maximal
+fn keystream_register(register: &RegisterOptions) -> Result<Lfsr, String> {
+    let reg = register.register(register.seed.unwrap())?;
+    if let Some(warning) = period_warning(&reg) {
+        eprintln!("{}", warning);
+    }
+    Ok(reg)
+}
+
+/// Xors the input with `keystream` packed in `bit_order`
+fn xor_keystream<K: Keystream>(keystream: K, bit_order: BitOrder, input: &str, output: &str) -> Result<(), String> {
+    let (mut input, mut output) = (open_input(input)?, create_output(output)?);
+    match bit_order {
+        BitOrder::Msb => xor_stream(&mut input, &mut output, &mut { keystream }),
+        BitOrder::Lsb => xor_stream(&mut input, &mut output, &mut LsbFirst(keystream)),
+    }.map_err(|e| e.to_string())
+}
+
+fn encrypt(register: &RegisterOptions, input: &str, output: &str) -> Result<(), String> {
+    xor_keystream(keystream_register(register)?, register.bit_order, input, output)
+}
+
+/// Xors the input with the keystream of the combination generator
+fn combine(combiner: Combiner, registers: &[(Polynomial, u64)], bit_order: BitOrder, input: &str, output: &str) -> Result<(), String> {
+    let mut regs = registers.iter()
+        .map(|(poly, seed)| Lfsr::with_polynomial(poly, *seed))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("incorrect register: {}", e))?
+        .into_iter();
+    let mut next = || regs.next().unwrap();
+    let selector_error = |e: Error| format!("incorrect selector: {}", e);
+    match combiner {
+        Combiner::Geffe => xor_keystream(Geffe::new(next(), next(), next()), bit_order, input, output),
+        Combiner::Shrinking => {
+            let generator = ShrinkingGenerator::new(next(), next()).map_err(selector_error)?;
+            xor_keystream(generator, bit_order, input, output)
+        }
+        Combiner::SelfShrinking => {
+            let generator = SelfShrinkingGenerator::new(next()).map_err(selector_error)?;
+            xor_keystream(generator, bit_order, input, output)
+        }
+        Combiner::AlternatingStep =>
+            xor_keystream(AlternatingStepGenerator::new(next(), next(), next()), bit_order, input, output),
+    }
+}
+
+fn keystream(register: &RegisterOptions, bytes: u64, output: &str) -> Result<(), String> {
+    let mut reg = keystream_register(register)?;
+    let mut output = create_output(output)?;
+    match register.bit_order {
+        BitOrder::Msb => write_keystream(&mut reg, bytes, &mut output),
+        BitOrder::Lsb => write_keystream(&mut LsbFirst(reg), bytes, &mut output),
+    }.map_err(|e| e.to_string())
+}
+
+/// Prints the order of the feedback polynomial, the period from the seed if given,
+/// and all cycles of short registers
+fn analyze(register: &RegisterOptions) -> Result<(), String> {
+    let reg = register.register(register.seed.unwrap_or(1))?;
+    let maximal = u64::MAX >> (64 - reg.length());
+    match reg.polynomial() {
+        Ok(poly) => {
+            println!("Feedback polynomial: {}, taps {:?}", poly, reg.taps());
+            println!("Irreducible: {}, primitive: {}", poly.is_irreducible().unwrap(), poly.is_primitive().unwrap());
+            println!("Order: {} (maximal period 2^{}-1 = {})", poly.order().unwrap(), reg.length(), maximal);
+        }
+        Err(_) => {
+            println!("Taps {:?} without tap 0: no feedback polynomial of degree {}", reg.taps(), reg.length());
+            println!("The lowest bit never feeds back, the register isn't invertible (maximal period 2^{}-1 = {})", reg.length(), maximal);
+        }
+    }
+    if let Some(seed) = register.seed {
+        println!("Period from seed {:x}: {}", seed, reg.period());
+    }
+    if reg.length() <= MAX_CYCLE_LENGTH {
+        print_cycles(&reg.cycle_structure().unwrap());
+    }
+    Ok(())
+}
+
+/// Points of the linear complexity profile to print: prefixes of 8, 16, 32... bits and the whole sequence
+fn profile_points(profile: &[usize]) -> Vec<(usize, usize)> {
+    let mut points: Vec<(usize, usize)> = (3..usize::BITS)
+        .map(|i| 1 << i)
+        .take_while(|&bits| bits < profile.len())
+        .map(|bits| (bits, profile[bits - 1]))
+        .collect();
+    if let Some(&last) = profile.last() {
+        points.push((profile.len(), last));
+    }
+    points
+}
+
+/// Prints the shortest register generating contents of the file and its linear complexity profile
+fn print_complexity(path: &str) -> Result<(), String> {
+    let bits = bits_from_bytes(&read_all(path)?);
+    let result = berlekamp_massey(&bits);
+    println!("Linear complexity: {} of {} bits", result.length, bits.len());
+    match result.polynomial() {
+        Ok(poly) => println!("Connection polynomial: {}", poly),
+        Err(_) => println!("Taps: {:?}", result.taps()),
+    }
+    if bits.is_empty() {
+        return Ok(());
+    }
+    println!("Profile (prefix bits: complexity, about half of the bits for random data):");
+    for (prefix, complexity) in profile_points(&result.profile) {
+        println!("{:>10}: {}", prefix, complexity);
+    }
+    // The profile never decreases, it first reaches the final length where the register is found
+    let last = result.profile.iter().position(|&l| l == result.length).unwrap();
+    println!("Complexity reached {} after {} bits", result.length, last + 1);
+    Ok(())
+}
+
+/// Prints p-values of the SP 800-22 statistical tests on contents of the file
+fn print_randomness(path: &str) -> Result<(), String> {
+    let report = Randomness::from_bytes(&read_all(path)?).report();
+    print!("{}", report);
+    if report.skipped() > 0 {
+        return Err(format!("{} tests couldn't run, the input is too short", report.skipped()));
+    }
+    if !report.passed() {
+        return Err("some tests failed".to_owned());
+    }
+    Ok(())
+}
+
+/// Recovers the register from ciphertext and its known prefix, then decrypts the whole file
+fn recover(cipher: &str, known: &str, output: &str) -> Result<(), String> {
+    let ciphertext = read_all(cipher)?;
+    let known = read_all(known)?;
+    let mut reg = recover_lfsr(&ciphertext, &known).map_err(|e| e.to_string())?;
+    match reg.polynomial() {
+        Ok(poly) => eprintln!("Feedback polynomial: {}, taps {:?}", poly, reg.taps()),
+        Err(_) => eprintln!("Taps: {:?}", reg.taps()),
+    }
+    eprintln!("Initial state: {:0width$x} ({} bits)", reg.state(), reg.length(), width = (reg.length() as usize).div_ceil(4));
+    xor_stream(&mut ciphertext.as_slice(), &mut create_output(output)?, &mut reg).map_err(|e| e.to_string())
+}
+
+/// Runs the correlation attack on keystream from the file for each register feedback polynomial.
+/// Registers without a significantly correlated seed are reported, and fail the command if none has one.
+fn attack_correlation(input: &str, polys: &[Polynomial]) -> Result<(), String> {
+    let bits = bits_from_bytes(&read_all(input)?);
+    let mut found = 0;
+    for poly in polys {
+        let taps = poly.taps().map_err(|e| e.to_string())?;
+        match correlation_attack(&bits, &taps, poly.length().unwrap()) {
+            Ok(best) => {
+                println!("{}: seed {:x}, correlation {:.3} over {} bits (agrees with {:.1}%), p-value {:.2e}",
+                    poly, best.seed, best.correlation(), best.bits, 100.0 * best.ratio(), best.p_value());
+                found += 1;
+            }
+            Err(Error::SeedNotFound) => println!("{}: no seed is correlated with the keystream", poly),
+            Err(e) => return Err(e.to_string()),
+        }
+    }
+    if found == 0 {
+        return Err("no register is correlated with the keystream".to_owned());
+    }
+    Ok(())
+}
+
+/// Recovers seeds of the Geffe generator with these register feedback polynomials
+fn attack_geffe_registers(input: &str, polys: &[Polynomial; 3]) -> Result<(), String> {
+    let bits = bits_from_bytes(&read_all(input)?);
+    let taps = polys.iter().map(|poly| poly.taps()).collect::<Result<Vec<_>, _>>().map_err(|e| e.to_string())?;
+    let registers = [0, 1, 2].map(|i| (taps[i].as_slice(), polys[i].length().unwrap()));
+    let seeds = attack_geffe(&bits, registers).map_err(|e| e.to_string())?;
+    for (name, (poly, seed)) in ["x1", "selector", "x3"].iter().zip(polys.iter().zip(seeds)) {
+        println!("{} ({}): seed {:x}", name, poly, seed);
+    }
+    Ok(())
+}
+
+/// Xors the input with A5/1 keystream
+fn encrypt_a51(key: u64, frame: u32, input: &str, output: &str) -> Result<(), String> {
+    let (mut input, mut output) = (open_input(input)?, create_output(output)?);
+    xor_stream(&mut input, &mut output, &mut A51::new(&key.to_be_bytes(), frame)).map_err(|e| e.to_string())
+}
+
+fn print_cycles(cycles: &lfsr::CycleStructure) {
+    for (len, count) in cycles.counts() {
+        println!("{} cycle(s) of length {}", count, len);
+    }
+    if !cycles.is_permutation() {
+        println!("{} states are not on any cycle", cycles.tail_states);
+    }
+}
+
+fn run(command: Command) -> Result<(), String> {
+    let nlfsr = |feedback, seed, length| Nlfsr::new(feedback, seed, length).map_err(|e| format!("incorrect register: {}", e));
+    match command {
+        Command::Help => {
+            println!("{}", USAGE);
+            Ok(())
+        }
+        Command::Encrypt { register, input, output } => encrypt(&register, &input, &output),
+        Command::Combine { combiner, registers, bit_order, input, output } =>
+            combine(combiner, &registers, bit_order, &input, &output),
+        Command::Keystream { register, bytes, output } => keystream(&register, bytes, &output),
+        Command::Analyze(register) => analyze(&register),
+        Command::Complexity(input) => print_complexity(&input),
+        Command::Randomness(input) => print_randomness(&input),
+        Command::Recover { cipher, known, output } => recover(&cipher, &known, &output),
+        Command::AttackCorrelation { input, polys } => attack_correlation(&input, &polys),
+        Command::AttackGeffe { input, polys } => attack_geffe_registers(&input, &polys),
+        Command::A51 { key, frame, input, output } => encrypt_a51(key, frame, &input, &output),
+        Command::NlfsrCycles { feedback, length } => {
+            print_cycles(&nlfsr(feedback, 0, length)?.cycle_structure().map_err(|e| e.to_string())?);
+            Ok(())
+        }
+        Command::NlfsrKeystream { feedback, length, seed, bytes, output } =>
+            write_keystream(&mut nlfsr(feedback, seed, length)?, bytes, &mut create_output(&output)?).map_err(|e| e.to_string()),
+    }
+}
+
+fn main() -> ExitCode {
+    match parse_args(env::args().skip(1)) {
+        Ok(command) => match run(command) {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(e) => {
+                eprintln!("lfsr: {}", e);
+                ExitCode::FAILURE
+            }
+        },
+        Err(e) => {
+            eprintln!("lfsr: {}\n\n{}", e, USAGE);
+            ExitCode::from(2)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(line: &str) -> Vec<String> {
+        line.split_whitespace().map(String::from).collect()
+    }
+
+    #[test]
+    fn parse_args_test() {
+        let parsed = parse_args(args("encrypt --taps 0,1,3,4 -l 32 --seed ABCD --bit-order lsb in.bin -")).unwrap();
+        assert_eq!(parsed, Command::Encrypt {
+            register: RegisterOptions {
+                taps: Some(vec![0, 1, 3, 4]),
+                poly: None,
+                length: Some(32),
+                seed: Some(0xABCD),
+                bit_order: BitOrder::Lsb,
+            },
+            input: "in.bin".to_owned(),
+            output: "-".to_owned(),
+        });
+
+        let poly: Polynomial = "x^4 + x + 1".parse().unwrap();
+        let mut analyze = vec!["analyze".to_owned(), "--poly".to_owned(), "x^4 + x + 1".to_owned()];
+        assert_eq!(parse_args(analyze.clone()).unwrap(), Command::Analyze(RegisterOptions {
+            poly: Some(poly.clone()),
+            ..Default::default()
+        }));
+        analyze.extend(["-l".to_owned(), "5".to_owned()]);
+        assert!(parse_args(analyze).is_err());
+
+        match parse_args(args("keystream 100 -s 1")).unwrap() {
+            Command::Keystream { register, bytes, output } => {
+                assert_eq!((register.seed, register.bit_order, bytes, output.as_str()), (Some(1), BitOrder::Msb, 100, "-"));
+            }
+            other => panic!("{:?}", other),
+        }
+
+        assert_eq!(parse_args(args("attack correlation ks.bin 0x6 0xC")).unwrap(), Command::AttackCorrelation {
+            input: "ks.bin".to_owned(),
+            polys: vec!["0x6".parse().unwrap(), "0xC".parse().unwrap()],
+        });
+        assert_eq!(parse_args(args("attack geffe ks.bin 0x6 0xC 0x12")).unwrap(), Command::AttackGeffe {
+            input: "ks.bin".to_owned(),
+            polys: ["0x6".parse().unwrap(), "0xC".parse().unwrap(), "0x12".parse().unwrap()],
+        });
+        assert_eq!(parse_args(args("complexity")).unwrap(), Command::Complexity("-".to_owned()));
+        assert_eq!(parse_args(args("a51 0123456789abcdef 22 in")).unwrap(), Command::A51 {
+            key: 0x0123456789abcdef,
+            frame: 22,
+            input: "in".to_owned(),
+            output: "-".to_owned(),
+        });
+        assert_eq!(parse_args(args("encrypt -s 1 --help")), Ok(Command::Help));
+
+        assert_eq!(parse_args(args("combine shrinking -p 0xC -s 1 -p 0x12 -s 2 --bit-order lsb in")).unwrap(), Command::Combine {
+            combiner: Combiner::Shrinking,
+            registers: vec![("0xC".parse().unwrap(), 1), ("0x12".parse().unwrap(), 2)],
+            bit_order: BitOrder::Lsb,
+            input: "in".to_owned(),
+            output: "-".to_owned(),
+        });
+    }
+
+    #[test]
+    fn parse_args_errors_test() {
+        assert!(parse_args(args("")).is_err());
+        assert!(parse_args(args("in.bin out.bin")).is_err());
+        assert!(parse_args(args("encrypt in out")).is_err());
+        assert!(parse_args(args("encrypt -s 1 a b c")).is_err());
+        assert!(parse_args(args("encrypt -s xyz")).is_err());
+        assert!(parse_args(args("encrypt -s 1 -t 0,1 -p 0x6")).is_err());
+        assert!(parse_args(args("encrypt -s 1 -t 0,x")).is_err());
+        assert!(parse_args(args("encrypt -s 1 -l 65")).is_err());
+        assert!(parse_args(args("encrypt -s 1 --bit-order middle")).is_err());
+        assert!(parse_args(args("encrypt -s")).is_err());
+        assert!(parse_args(args("keystream -s 1")).is_err());
+        assert!(parse_args(args("keystream ten -s 1")).is_err());
+        assert!(parse_args(args("analyze extra")).is_err());
+        assert!(parse_args(args("complexity -s 1 in")).is_err());
+        assert!(parse_args(args("a51 0123 4194304")).is_err());
+        assert!(parse_args(args("attack correlation ks.bin")).is_err());
+        assert!(parse_args(args("attack geffe ks.bin 0x6 0xC")).is_err());
+        assert!(parse_args(args("encrypt --bogus")).is_err());
+        assert!(parse_args(args("encrypt -s 1 -s 2")).is_err());
+        assert!(parse_args(args("combine geffe -p 0xC -s 1 -p 0x12 -s 2")).is_err());
+        assert!(parse_args(args("combine self-shrinking -p 0xC -s 1 -l 4")).is_err());
+        assert!(parse_args(args("combine summation -p 0xC -s 1")).is_err());
+        assert!(parse_args(args("combine -p 0xC -s 1")).is_err());
+    }
+
+    #[test]
+    fn register_test() {
+        let options = |line: &str| match parse_args(args(line)).unwrap() {
+            Command::Analyze(register) => register,
+            other => panic!("{:?}", other),
+        };
+        let reg = options("analyze -l 16").register(1).unwrap();
+        assert_eq!(reg.polynomial(), Ok(primitive_polynomial(16).unwrap()));
+        assert_eq!(options("analyze").register(1).unwrap().length(), 64);
+        assert_eq!(options("analyze -t 0,1,3,4").register(1).unwrap().taps(), [0, 1, 3, 4]);
+        assert_eq!(options("analyze -p 0xC").register(5).unwrap().state(), 5);
+        assert!(options("analyze -l 1").register(1).is_err());
+        assert!(options("analyze -l 4").register(0x10).is_err());
+    }
+
+    #[test]
+    fn bit_order_test() {
+        let reg = Lfsr::new(&[0, 1, 3, 4], 0x1234_5678_9ABC_DEF0, 64).unwrap();
+        let mut expected = [0u8; 20];
+        reg.clone().fill_bytes(&mut expected);
+        expected.iter_mut().for_each(|b| *b = b.reverse_bits());
+
+        let mut bytes = [0u8; 20];
+        LsbFirst(reg.clone()).fill_bytes(&mut bytes);
+        assert_eq!(bytes, expected);
+        let mut lsb = LsbFirst(reg.clone());
+        assert_eq!((0..20).map(|_| lsb.next_byte()).collect::<Vec<_>>(), expected);
+        let mut data = [0xA5u8; 20];
+        LsbFirst(reg).apply_keystream(&mut data);
+        assert!(data.iter().zip(expected).all(|(&d, e)| d == 0xA5 ^ e));
+    }
+
+    #[test]
+    fn recover_test() {
+        let dir = env::temp_dir().join(format!("lfsr-cli-recover-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = |name: &str| dir.join(name).to_str().unwrap().to_owned();
+        let command = |line: &[&str]| parse_args(line.iter().map(|&a| a.to_owned())).unwrap();
+
+        let plain: Vec<u8> = b"%PDF-1.7 known header".iter().copied().chain((0..5000u32).map(|i| (i * 13 + i / 7) as u8)).collect();
+        std::fs::write(path("plain"), &plain).unwrap();
+        std::fs::write(path("known"), &plain[..17]).unwrap();
+        run(command(&["encrypt", "-s", "deadbeef0badf00d", &path("plain"), &path("cipher")])).unwrap();
+        assert_ne!(std::fs::read(path("cipher")).unwrap(), plain);
+
+        run(command(&["recover", &path("cipher"), &path("known"), &path("decrypted")])).unwrap();
+        assert_eq!(std::fs::read(path("decrypted")).unwrap(), plain);
+
+        // One byte short of the 17 a 64 bit register needs
+        std::fs::write(path("known"), &plain[..16]).unwrap();
+        let err = run(command(&["recover", &path("cipher"), &path("known"), &path("decrypted")])).unwrap_err();
+        assert_eq!(err, Error::KeystreamTooShort(136).to_string());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn combine_test() {
+        let dir = env::temp_dir().join(format!("lfsr-cli-combine-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = |name: &str| dir.join(name).to_str().unwrap().to_owned();
+        let command = |line: &[&str]| parse_args(line.iter().map(|&a| a.to_owned())).unwrap();
+
+        let plain: Vec<u8> = (0..3000u32).map(|i| (i * 7 + i / 5) as u8).collect();
+        std::fs::write(path("plain"), &plain).unwrap();
+        let polys = ["x^31 + x^3 + 1", "x^29 + x^2 + 1", "x^23 + x^5 + 1"].map(|p| p.parse::<Polynomial>().unwrap());
+        let reg = |i: usize, seed| Lfsr::with_polynomial(&polys[i], seed).unwrap();
+        let registers = ["-p", "x^31 + x^3 + 1", "-s", "1234", "-p", "x^29 + x^2 + 1", "-s", "5678", "-p", "x^23 + x^5 + 1", "-s", "9abc"];
+        let cases: [(&str, usize, Box<dyn Keystream>); 4] = [
+            ("geffe", 3, Box::new(Geffe::new(reg(0, 0x1234), reg(1, 0x5678), reg(2, 0x9abc)))),
+            ("shrinking", 2, Box::new(ShrinkingGenerator::new(reg(0, 0x1234), reg(1, 0x5678)).unwrap())),
+            ("self-shrinking", 1, Box::new(SelfShrinkingGenerator::new(reg(0, 0x1234)).unwrap())),
+            ("alternating-step", 3, Box::new(AlternatingStepGenerator::new(reg(0, 0x1234), reg(1, 0x5678), reg(2, 0x9abc)))),
+        ];
+        let (plain_path, cipher_path) = (path("plain"), path("cipher"));
+        for (kind, count, mut generator) in cases {
+            let mut line = vec!["combine", kind];
+            line.extend(&registers[..4 * count]);
+            line.extend([plain_path.as_str(), cipher_path.as_str()]);
+            run(command(&line)).unwrap();
+            let mut expected = plain.clone();
+            generator.apply_keystream(&mut expected);
+            assert_eq!(std::fs::read(&cipher_path).unwrap(), expected, "{}", kind);
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn attack_correlation_test() {
+        let path = env::temp_dir().join(format!("lfsr-cli-attack-{}", std::process::id()));
+        let polys = [11, 12, 13].map(|n| primitive_polynomial(n).unwrap());
+        let reg = |i: usize, seed| Lfsr::with_polynomial(&polys[i], seed).unwrap();
+        let mut keystream = vec![0u8; 40];
+        Geffe::new(reg(0, 0x5A3), reg(1, 0xBEE), reg(2, 0x1234)).fill_bytes(&mut keystream);
+        std::fs::write(&path, &keystream).unwrap();
+        let input = path.to_str().unwrap();
+
+        assert_eq!(attack_correlation(input, &polys), Ok(()));
+        // The selector alone isn't correlated with the output
+        assert_eq!(attack_correlation(input, &polys[1..2]), Err("no register is correlated with the keystream".to_owned()));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn profile_points_test() {
+        assert_eq!(profile_points(&[]), []);
+        assert_eq!(profile_points(&[1, 1, 2]), [(3, 2)]);
+        let profile: Vec<usize> = (1..=20).map(|i: usize| i.div_ceil(2)).collect();
+        assert_eq!(profile_points(&profile), [(8, 4), (16, 8), (20, 10)]);
+        assert_eq!(profile_points(&profile[..16]), [(8, 4), (16, 8)]);
+    }
+
+    #[test]
+    fn period_warning_test() {
+        assert_eq!(period_warning(&Lfsr::with_polynomial(&primitive_polynomial(16).unwrap(), 1).unwrap()), None);
+        // x^4 + x^2 + 1 and taps without 0 both repeat early
+        let warning = period_warning(&Lfsr::new(&[0, 2], 1, 4).unwrap()).unwrap();
+        assert!(warning.starts_with("Warning: keystream period is 6,") && warning.contains("x^4 + x^2 + 1 is not primitive"), "{}", warning);
+        let warning = period_warning(&Lfsr::new(&[1, 2], 0b1000, 4).unwrap()).unwrap();
+        assert!(warning.starts_with("Warning: keystream period is 7,") && warning.contains("no tap 0"), "{}", warning);
+    }
+}
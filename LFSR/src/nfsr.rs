@@ -0,0 +1,115 @@
+use crate::berlekamp_massey::word_at;
+use crate::long::check_words;
+use crate::{Error, Keystream};
+
+/// Bit `i` of a register stored in 64 bit words
+pub fn state_bit(state: &[u64], i: u32) -> u8 {
+    (state[i as usize / 64] >> (i % 64) & 1) as u8
+}
+
+/// Feedback function of a `Nfsr`, computes the new top bit from the register state
+pub trait Feedback {
+    fn feedback(&self, state: &[u64]) -> u8;
+}
+
+impl<F: Fn(&[u64]) -> u8> Feedback for F {
+    fn feedback(&self, state: &[u64]) -> u8 {
+        self(state)
+    }
+}
+
+/// Shift register with arbitrary, usually nonlinear, feedback.
+///
+/// Shifts the same way as `Lfsr`: outputs bit 0, shifts right and puts the
+/// result of `feedback` into the top bit. Bits are stored in 64 bit words like
+/// in `LongLfsr`, `state_bit` reads them. Unlike linear registers, all zero state
+/// isn't necessarily stuck, so it is allowed.
+#[derive(Clone, Debug)]
+pub struct Nfsr<F = fn(&[u64]) -> u8> {
+    reg: Vec<u64>,
+    bit_len: u32,
+    feedback: F,
+}
+
+impl<F: Feedback> Nfsr<F> {
+    /// Creates a register of `length` bits, initialized with `init` words, lowest first
+    pub fn new(feedback: F, init: &[u64], length: u32) -> Result<Self, Error> {
+        check_words(init, length)?;
+        let mut reg = init.to_vec();
+        reg.resize(length.div_ceil(64) as usize, 0);
+        Ok(Nfsr { reg, bit_len: length, feedback })
+    }
+
+    /// Current contents of the register
+    pub fn state(&self) -> &[u64] {
+        &self.reg
+    }
+
+    pub fn length(&self) -> u32 {
+        self.bit_len
+    }
+
+    pub fn feedback(&self) -> &F {
+        &self.feedback
+    }
+
+    /// Bit `i` of the register, bit 0 is the next output
+    pub fn bit(&self, i: u32) -> u8 {
+        state_bit(&self.reg, i)
+    }
+
+    /// Clocks the register once with `input` xored into the feedback bit and returns
+    /// the output bit. Ciphers made of several registers feed them into each other this way.
+    pub fn clock_with_input(&mut self, input: u8) -> u8 {
+        let new_bit = (self.feedback.feedback(&self.reg) ^ input) & 1;
+        let out = self.reg[0] & 1;
+        for i in 0..self.reg.len() {
+            self.reg[i] = word_at(&self.reg, 64 * i + 1);
+        }
+        let top = self.bit_len - 1;
+        self.reg[top as usize / 64] |= (new_bit as u64) << (top % 64);
+        out as u8
+    }
+}
+
+impl<F: Feedback> Keystream for Nfsr<F> {
+    fn next_bit(&mut self) -> u8 {
+        self.clock_with_input(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Lfsr, LongLfsr};
+
+    #[test]
+    fn linear_feedback_test() {
+        // Linear feedback gives the same as `Lfsr`
+        let mut nfsr = Nfsr::new(|s: &[u64]| state_bit(s, 0) ^ state_bit(s, 1) ^ state_bit(s, 3) ^ state_bit(s, 4),
+            &[0xABCDABCD_ABCDABCD], 64).unwrap();
+        let mut lfsr = Lfsr::new(&[0, 1, 3, 4], 0xABCDABCD_ABCDABCD, 64).unwrap();
+        for _ in 0..1000 {
+            assert_eq!(nfsr.next_bit(), lfsr.next_bit());
+        }
+
+        let mut nfsr = Nfsr::new(|s: &[u64]| state_bit(s, 0) ^ state_bit(s, 99), &[3, 1 << 35], 100).unwrap();
+        let mut lfsr = LongLfsr::new(&[0, 99], &[3, 1 << 35], 100).unwrap();
+        for _ in 0..1000 {
+            assert_eq!(nfsr.next_bit(), lfsr.next_bit());
+        }
+    }
+
+    #[test]
+    fn nonlinear_feedback_test() {
+        // Feedback s0 + s1*s2 on 3 bits goes through 110 -> 111 -> 011 -> 101 -> 110
+        let mut nfsr = Nfsr::new(|s: &[u64]| state_bit(s, 0) ^ state_bit(s, 1) & state_bit(s, 2), &[0b110], 3).unwrap();
+        let bits: Vec<u8> = (0..8).map(|_| nfsr.next_bit()).collect();
+        assert_eq!(bits, [0, 1, 1, 1, 0, 1, 1, 1]);
+
+        let mut input = Nfsr::new(|_: &[u64]| 0, &[0], 3).unwrap();
+        assert_eq!(input.clock_with_input(1), 0);
+        assert_eq!(input.state(), [0b100]);
+        assert!(matches!(Nfsr::new(|_: &[u64]| 0, &[0b1000], 3), Err(Error::SeedTooWide)));
+    }
+}
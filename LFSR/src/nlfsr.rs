@@ -0,0 +1,104 @@
+use crate::cycles::{cycle_structure, CycleStructure};
+use crate::long::check_words;
+use crate::nfsr::Nfsr;
+use crate::{Anf, Error, Keystream};
+
+/// Nonlinear feedback shift register, up to 64 bits long, with feedback
+/// given in algebraic normal form over the state bits.
+///
+/// Shifts the same way as `Lfsr`, the new top bit is `feedback` evaluated
+/// with `xi` being bit `i` of the register.
+#[derive(Clone, Debug)]
+pub struct Nlfsr {
+    nfsr: Nfsr<Anf>,
+}
+
+impl Nlfsr {
+    /// Creates a register of `length` bits, initialized with `init`
+    pub fn new(feedback: Anf, init: u64, length: u8) -> Result<Self, Error> {
+        if length > 64 { return Err(Error::InvalidLength(length as u32)); }
+        check_words(&[init], length as u32)?;
+        let vars = feedback.variables();
+        if length < 64 && vars >> length != 0 {
+            return Err(Error::TapOutOfRange(63 - vars.leading_zeros()));
+        }
+        Ok(Nlfsr { nfsr: Nfsr::new(feedback, &[init], length as u32)? })
+    }
+
+    /// Current contents of the register
+    pub fn state(&self) -> u64 {
+        self.nfsr.state()[0]
+    }
+
+    pub fn feedback(&self) -> &Anf {
+        self.nfsr.feedback()
+    }
+
+    pub fn length(&self) -> u8 {
+        self.nfsr.length() as u8
+    }
+
+    /// Cycles of the state graph, for registers up to `MAX_CYCLE_LENGTH` bits
+    pub fn cycle_structure(&self) -> Result<CycleStructure, Error> {
+        let (anf, top) = (self.feedback(), self.length() - 1);
+        cycle_structure(self.length(), |s| s >> 1 | (anf.eval(s) as u64) << top)
+    }
+}
+
+impl Keystream for Nlfsr {
+    fn next_bit(&mut self) -> u8 {
+        self.nfsr.next_bit()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Lfsr;
+
+    #[test]
+    fn keystream_test() {
+        let mut nlfsr = Nlfsr::new("x0 + x1*x2".parse().unwrap(), 0b110, 3).unwrap();
+        let bits: Vec<u8> = (0..8).map(|_| nlfsr.next_bit()).collect();
+        assert_eq!(bits, [0, 1, 1, 1, 0, 1, 1, 1]);
+
+        // Linear feedback is the same as `Lfsr`
+        let mut nlfsr = Nlfsr::new("x0 + x1 + x3 + x4".parse().unwrap(), 0xABCD, 64).unwrap();
+        let mut lfsr = Lfsr::new(&[0, 1, 3, 4], 0xABCD, 64).unwrap();
+        assert!((0..1000).all(|_| nlfsr.next_bit() == lfsr.next_bit()));
+    }
+
+    #[test]
+    fn cycle_structure_test() {
+        let nlfsr = Nlfsr::new("x0 + x1*x2".parse().unwrap(), 1, 3).unwrap();
+        let cycles = nlfsr.cycle_structure().unwrap();
+        assert_eq!(cycles.cycles, [(1, 0), (3, 1), (4, 3)]);
+        assert!(cycles.is_permutation());
+
+        let nlfsr = Nlfsr::new("x0 + x3 + x1*x2".parse().unwrap(), 1, 4).unwrap();
+        assert_eq!(nlfsr.cycle_structure().unwrap().counts(), [(1, 2), (5, 1), (9, 1)]);
+
+        // Without x0 the update isn't invertible and states merge
+        let nlfsr = Nlfsr::new("x1*x2".parse().unwrap(), 1, 4).unwrap();
+        let cycles = nlfsr.cycle_structure().unwrap();
+        assert_eq!((cycles.cycles.as_slice(), cycles.tail_states), ([(1, 0), (1, 15)].as_slice(), 14));
+        assert!(!cycles.is_permutation());
+
+        // De Bruijn sequence: x0 + x1 with the all zero state of x1..x3 spliced in
+        let de_bruijn = "x0 + x1 + 1 + x1 + x2 + x3 + x1*x2 + x1*x3 + x2*x3 + x1*x2*x3".parse().unwrap();
+        let nlfsr = Nlfsr::new(de_bruijn, 0, 4).unwrap();
+        assert_eq!(nlfsr.cycle_structure().unwrap().cycles, [(16, 0)]);
+        assert_eq!(nlfsr.feedback().to_string(), "x0 + x1*x2 + x1*x2*x3 + x1*x3 + x2 + x2*x3 + x3 + 1");
+
+        let long = Nlfsr::new("x0 + x1".parse().unwrap(), 1, 25).unwrap();
+        assert_eq!(long.cycle_structure().unwrap_err(), Error::InvalidLength(25));
+    }
+
+    #[test]
+    fn new_errors_test() {
+        assert_eq!(Nlfsr::new("x0 + x8".parse().unwrap(), 1, 8).unwrap_err(), Error::TapOutOfRange(8));
+        assert_eq!(Nlfsr::new("x0 + x1".parse().unwrap(), 0x100, 8).unwrap_err(), Error::SeedTooWide);
+        assert_eq!(Nlfsr::new("x0".parse().unwrap(), 1, 65).unwrap_err(), Error::InvalidLength(65));
+        assert!(Nlfsr::new("x0 + x63".parse().unwrap(), u64::MAX, 64).is_ok());
+    }
+}
@@ -0,0 +1,89 @@
+use crate::cycles::{cycle_structure, CycleStructure};
+use crate::{berlekamp_massey, gf2, Error, Keystream, Lfsr, Polynomial};
+
+impl Polynomial {
+    /// Smallest `e` such that the polynomial divides `x^e + 1`, the longest period
+    /// of a register with this feedback polynomial. Computed from the factorization
+    /// of the polynomial, so it is fast for any degree up to 64.
+    pub fn order(&self) -> Result<u64, Error> {
+        Ok(gf2::order(self.bits()?))
+    }
+}
+
+impl Lfsr {
+    /// Period of the output sequence starting from the current state.
+    ///
+    /// The next `length` output bits are the state itself, so the period is the order
+    /// of the connection polynomial found by `berlekamp_massey` from `2 * length` bits.
+    /// Without tap 0 the register isn't invertible and the state may enter
+    /// its cycle only after a few clocks, this is the length of that cycle.
+    pub fn period(&self) -> u64 {
+        let mut reg = self.clone();
+        let bits: Vec<u8> = (0..2 * self.length()).map(|_| reg.next_bit()).collect();
+        let connection = berlekamp_massey(&bits).connection;
+        gf2::order(connection.iter().rev().fold(0u128, |acc, &c| acc << 1 | c as u128))
+    }
+
+    /// Cycles of the state graph, for registers up to `MAX_CYCLE_LENGTH` bits.
+    /// The all zero state, not allowed as a seed, makes a cycle of length 1.
+    pub fn cycle_structure(&self) -> Result<CycleStructure, Error> {
+        let mask = self.taps().iter().fold(0u64, |acc, &tap| acc | 1 << tap);
+        let top = self.length() - 1;
+        cycle_structure(self.length(), |s| s >> 1 | (((s & mask).count_ones() & 1) as u64) << top)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::primitive_polynomial;
+
+    #[test]
+    fn order_test() {
+        let order = |s: &str| s.parse::<Polynomial>().unwrap().order().unwrap();
+        assert_eq!(order("x^4 + x + 1"), 15);
+        assert_eq!(order("x^4 + x^3 + x^2 + x + 1"), 5);
+        assert_eq!(order("x^4 + x^2 + 1"), 6);
+        assert_eq!(order("x^64 + x^4 + x^3 + x + 1"), u64::MAX);
+        // x^64 + x + 1 splits into x^4 + x + 1 and five factors of degree 12
+        assert_eq!(order("x^64 + x + 1"), 4095);
+        for n in 2..=64 {
+            assert_eq!(primitive_polynomial(n).unwrap().order().unwrap(), u64::MAX >> (64 - n));
+        }
+    }
+
+    #[test]
+    fn brute_force_test() {
+        // The order is the longest cycle, each seed's period the length of its cycle
+        for n in 2..=10u8 {
+            for middle in 0..1u64 << (n - 1) {
+                let Ok(poly) = Polynomial::from_mask(1 << (n - 1) | middle) else { continue };
+                let lfsr = Lfsr::with_polynomial(&poly, 1).unwrap();
+                let cycles = lfsr.cycle_structure().unwrap();
+                assert!(cycles.is_permutation());
+                assert_eq!(poly.order().unwrap(), cycles.cycles.last().unwrap().0, "{}", poly);
+                for &(len, seed) in cycles.cycles.iter().filter(|&&(_, seed)| seed != 0) {
+                    assert_eq!(Lfsr::with_polynomial(&poly, seed).unwrap().period(), len, "{} {:x}", poly, seed);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn cycle_structure_test() {
+        // (x^2 + x + 1)^2: 0, one cycle of x^2 + x + 1 states and two of length 6
+        let lfsr = Lfsr::with_polynomial(&"x^4 + x^2 + 1".parse().unwrap(), 1).unwrap();
+        assert_eq!(lfsr.cycle_structure().unwrap().counts(), [(1, 1), (3, 1), (6, 2)]);
+        assert_eq!(lfsr.period(), 6);
+
+        // Without tap 0 the lowest bit falls off and states merge into x^3 + x + 1 cycles
+        let lfsr = Lfsr::new(&[1, 2], 0b1000, 4).unwrap();
+        let cycles = lfsr.cycle_structure().unwrap();
+        assert_eq!((cycles.counts(), cycles.tail_states), (vec![(1, 1), (7, 1)], 8));
+        assert_eq!(lfsr.period(), 7);
+
+        let long = Lfsr::with_polynomial(&primitive_polynomial(25).unwrap(), 1).unwrap();
+        assert_eq!(long.cycle_structure().unwrap_err(), Error::InvalidLength(25));
+        assert_eq!(long.period(), (1 << 25) - 1);
+    }
+}
@@ -0,0 +1,196 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::{Error, Lfsr};
+
+/// Feedback polynomial of a register over GF(2), such as `x^64 + x^63 + x^61 + x^60 + 1`.
+///
+/// Polynomial of degree `n` describes a register of `n` bits, each term `x^e`
+/// except the constant one corresponds to tap `n - e`, so the example above
+/// is a 64 bit register with taps `0, 1, 3, 4`. In hex form, bit `e - 1` of the
+/// number stands for `x^e`, which gives the mask of the equivalent `GaloisLfsr`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Polynomial {
+    /// Exponents of non-zero terms in descending order, always ending with 0
+    terms: Vec<u32>,
+}
+
+impl Polynomial {
+    /// Builds polynomial from exponents of its terms. Constant term is always added.
+    pub fn from_terms(terms: &[u32]) -> Result<Self, Error> {
+        let mut terms = terms.to_vec();
+        terms.sort_unstable_by(|a, b| b.cmp(a));
+        if terms.last() != Some(&0) { terms.push(0); }
+        if let Some(w) = terms.windows(2).find(|w| w[0] == w[1]) {
+            return Err(Error::InvalidPolynomial(format!("term x^{} is repeated", w[0])));
+        }
+        if terms.len() < 3 {
+            return Err(Error::TooFewTaps);
+        }
+        Ok(Polynomial { terms })
+    }
+
+    /// Polynomial of a register with `taps` and `length` bits. Tap 0 gives the `x^length`
+    /// term, without it the output bit never comes back into the register and the
+    /// register has no feedback polynomial of its length.
+    pub fn from_taps(taps: &[u8], length: u8) -> Result<Self, Error> {
+        if let Some(&tap) = taps.iter().find(|&&tap| tap >= length) {
+            return Err(Error::TapOutOfRange(tap as u32));
+        }
+        if !taps.contains(&0) {
+            return Err(Error::MissingTapZero);
+        }
+        Self::from_terms(&taps.iter().map(|&tap| (length - tap) as u32).collect::<Vec<_>>())
+    }
+
+    /// Polynomial from a `GaloisLfsr` mask
+    pub fn from_mask(mask: u64) -> Result<Self, Error> {
+        Self::from_terms(&(0..64).filter(|bit| mask >> bit & 1 == 1).map(|bit| bit + 1).collect::<Vec<_>>())
+    }
+
+    pub fn degree(&self) -> u32 {
+        self.terms[0]
+    }
+
+    /// Exponents of non-zero terms in descending order
+    pub fn terms(&self) -> &[u32] {
+        &self.terms
+    }
+
+    /// Register length for this polynomial, if it fits into 64 bits
+    pub fn length(&self) -> Result<u8, Error> {
+        if self.degree() > 64 { return Err(Error::InvalidLength(self.degree())); }
+        Ok(self.degree() as u8)
+    }
+
+    /// Taps of a `Lfsr` with this feedback polynomial, in ascending order
+    pub fn taps(&self) -> Result<Vec<u8>, Error> {
+        let length = self.length()?;
+        Ok(self.terms[..self.terms.len()-1].iter().map(|&e| length - e as u8).collect())
+    }
+
+    /// Mask of a `GaloisLfsr` with this feedback polynomial
+    pub fn mask(&self) -> Result<u64, Error> {
+        self.length()?;
+        Ok(self.terms[..self.terms.len()-1].iter().fold(0u64, |acc, &e| acc | 1 << (e - 1)))
+    }
+}
+
+impl fmt::Display for Polynomial {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, &e) in self.terms.iter().enumerate() {
+            if i > 0 { f.write_str(" + ")?; }
+            match e {
+                0 => f.write_str("1")?,
+                1 => f.write_str("x")?,
+                _ => write!(f, "x^{}", e)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Polynomial {
+    type Err = Error;
+
+    /// Parses either `x^4 + x + 1` notation or a hex mask like `0xC`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if let Some(hex) = s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+            let mask = u64::from_str_radix(hex, 16)
+                .map_err(|_| Error::InvalidPolynomial(format!("'{}' is not a 64 bit hex number", s)))?;
+            return Self::from_mask(mask);
+        }
+
+        let mut terms = Vec::new();
+        for term in s.split('+').map(str::trim) {
+            let exp = match term {
+                "1" => 0,
+                "x" | "X" => 1,
+                _ => term.strip_prefix("x^").or_else(|| term.strip_prefix("X^"))
+                    .and_then(|e| e.trim().parse().ok())
+                    .ok_or_else(|| Error::InvalidPolynomial(format!("can't parse term '{}'", term)))?,
+            };
+            terms.push(exp);
+        }
+        if !terms.contains(&0) {
+            return Err(Error::InvalidPolynomial("feedback polynomial should have constant term 1".to_owned()));
+        }
+        Self::from_terms(&terms)
+    }
+}
+
+impl Lfsr {
+    /// Creates a register with feedback polynomial `poly`, initialized with `init`
+    pub fn with_polynomial(poly: &Polynomial, init: u64) -> Result<Self, Error> {
+        Lfsr::new(&poly.taps()?, init, poly.length()?)
+    }
+
+    /// Feedback polynomial, an error for registers without tap 0
+    pub fn polynomial(&self) -> Result<Polynomial, Error> {
+        Polynomial::from_taps(self.taps(), self.length())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{taps_to_mask, Keystream};
+
+    #[test]
+    fn parse_test() {
+        let poly: Polynomial = "x^64 + x^63 + x^61 + x^60 + 1".parse().unwrap();
+        assert_eq!(poly.degree(), 64);
+        assert_eq!(poly.taps().unwrap(), [0, 1, 3, 4]);
+        assert_eq!(poly.mask().unwrap(), taps_to_mask(&[0, 1, 3, 4], 64));
+
+        let poly: Polynomial = "1+x+X^4".parse().unwrap();
+        assert_eq!(poly.terms(), [4, 1, 0]);
+        assert_eq!(poly.taps().unwrap(), [0, 3]);
+
+        let poly: Polynomial = "0xB400".parse().unwrap();
+        assert_eq!(poly.to_string(), "x^16 + x^14 + x^13 + x^11 + 1");
+        assert_eq!(poly.mask().unwrap(), 0xB400);
+    }
+
+    #[test]
+    fn parse_errors_test() {
+        assert!(matches!("x^4 + x".parse::<Polynomial>(), Err(Error::InvalidPolynomial(_))));
+        assert!(matches!("x^4 + y + 1".parse::<Polynomial>(), Err(Error::InvalidPolynomial(_))));
+        assert!(matches!("x^4 + x^4 + 1".parse::<Polynomial>(), Err(Error::InvalidPolynomial(_))));
+        assert!(matches!("0xZZ".parse::<Polynomial>(), Err(Error::InvalidPolynomial(_))));
+        assert_eq!("x^4 + 1".parse::<Polynomial>(), Err(Error::TooFewTaps));
+        let long: Polynomial = "x^127 + x + 1".parse().unwrap();
+        assert_eq!(long.taps(), Err(Error::InvalidLength(127)));
+    }
+
+    #[test]
+    fn round_trip_test() {
+        for s in ["x^64 + x^4 + x^3 + x + 1", "x^5 + x^2 + 1", "x^2 + x + 1", "x^127 + x + 1"] {
+            assert_eq!(s.parse::<Polynomial>().unwrap().to_string(), s);
+        }
+        let poly = Polynomial::from_taps(&[0, 2, 5, 7], 8).unwrap();
+        assert_eq!(Polynomial::from_mask(poly.mask().unwrap()).unwrap(), poly);
+        assert_eq!(poly.to_string().parse::<Polynomial>().unwrap(), poly);
+
+        let lfsr = Lfsr::new(&[0, 2, 5, 7], 0x5A, 8).unwrap();
+        let back = Lfsr::with_polynomial(&lfsr.polynomial().unwrap(), 0x5A).unwrap();
+        assert_eq!((back.taps(), back.length()), (lfsr.taps(), lfsr.length()));
+
+        // Without tap 0 a polynomial of lower degree would describe a shorter register
+        let no_tap0 = Lfsr::new(&[1, 2], 0b1000, 4).unwrap();
+        assert_eq!(no_tap0.polynomial(), Err(Error::MissingTapZero));
+        assert_eq!(Polynomial::from_taps(&[1, 2], 4), Err(Error::MissingTapZero));
+    }
+
+    #[test]
+    fn with_polynomial_test() {
+        let poly: Polynomial = "x^64 + x^63 + x^61 + x^60 + 1".parse().unwrap();
+        let mut a = Lfsr::with_polynomial(&poly, 0xABCD).unwrap();
+        let mut b = Lfsr::new(&[0, 1, 3, 4], 0xABCD, 64).unwrap();
+        assert_eq!(a.polynomial(), Ok(poly));
+        for _ in 0..100 {
+            assert_eq!(a.next_byte(), b.next_byte());
+        }
+    }
+}
@@ -0,0 +1,115 @@
+use crate::{gf2, Error, Lfsr, Polynomial};
+
+/// Middle terms of a primitive polynomial `x^n + ... + 1` for each length `n`,
+/// trinomials where they exist and pentanomials otherwise.
+const PRIMITIVE_TERMS: [&[u32]; 65] = [
+    &[], &[],
+    &[1], &[1], &[1], &[2], &[1], &[1], &[4, 3, 2], &[4],
+    &[3], &[2], &[6, 4, 1], &[4, 3, 1], &[5, 3, 1], &[1], &[5, 3, 2], &[3], &[7], &[5, 2, 1],
+    &[3], &[2], &[1], &[5], &[4, 3, 1], &[3], &[6, 2, 1], &[5, 2, 1], &[3], &[2],
+    &[6, 4, 1], &[3], &[7, 6, 2], &[13], &[8, 4, 3], &[2], &[11], &[6, 4, 1], &[6, 5, 1], &[4],
+    &[5, 4, 3], &[3], &[7, 4, 3], &[6, 4, 3], &[6, 5, 2], &[4, 3, 1], &[8, 7, 6], &[5], &[9, 7, 4], &[9],
+    &[4, 3, 2], &[6, 3, 1], &[3], &[6, 2, 1], &[8, 6, 3], &[24], &[7, 4, 2], &[7], &[19], &[7, 4, 2],
+    &[1], &[5, 2, 1], &[6, 5, 3], &[1], &[4, 3, 1],
+];
+
+/// Known primitive polynomial of degree `length`, for lengths 2 to 64
+pub fn primitive_polynomial(length: u8) -> Option<Polynomial> {
+    let middle = PRIMITIVE_TERMS.get(length as usize).filter(|t| !t.is_empty())?;
+    let mut terms = vec![length as u32];
+    terms.extend_from_slice(middle);
+    Some(Polynomial::from_terms(&terms).unwrap())
+}
+
+impl Polynomial {
+    /// Dense form of the polynomial for degrees up to 64
+    pub(crate) fn bits(&self) -> Result<u128, Error> {
+        self.length()?;
+        Ok(self.terms().iter().fold(0u128, |acc, &e| acc | 1 << e))
+    }
+
+    /// Whether the polynomial has no factors other than 1 and itself
+    pub fn is_irreducible(&self) -> Result<bool, Error> {
+        Ok(gf2::is_irreducible(self.bits()?))
+    }
+
+    /// Whether a register with this polynomial goes through all `2^n - 1`
+    /// non-zero states, that is the polynomial is irreducible and `x` has
+    /// order `2^n - 1` modulo it.
+    pub fn is_primitive(&self) -> Result<bool, Error> {
+        Ok(gf2::is_primitive(self.bits()?))
+    }
+}
+
+impl Lfsr {
+    /// Same as `with_polynomial`, but only accepts primitive polynomials,
+    /// which guarantees the maximal period of `2^n - 1`
+    pub fn with_primitive_polynomial(poly: &Polynomial, init: u64) -> Result<Self, Error> {
+        if !poly.is_primitive()? { return Err(Error::NotPrimitive); }
+        Lfsr::with_polynomial(poly, init)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Keystream;
+
+    fn period(lfsr: &mut Lfsr) -> u64 {
+        let start = lfsr.state();
+        let mut period = 0;
+        loop {
+            lfsr.next_bit();
+            period += 1;
+            if lfsr.state() == start { return period; }
+        }
+    }
+
+    #[test]
+    fn table_test() {
+        assert!(primitive_polynomial(1).is_none());
+        assert!(primitive_polynomial(65).is_none());
+        assert_eq!(primitive_polynomial(64).unwrap().to_string(), "x^64 + x^4 + x^3 + x + 1");
+        for n in 2..=64 {
+            let poly = primitive_polynomial(n).unwrap();
+            assert_eq!(poly.degree(), n as u32);
+            assert!(poly.is_primitive().unwrap(), "{}", poly);
+        }
+        for n in 2..=16 {
+            let mut lfsr = Lfsr::with_primitive_polynomial(&primitive_polynomial(n).unwrap(), 1).unwrap();
+            assert_eq!(period(&mut lfsr), (1 << n) - 1);
+        }
+    }
+
+    #[test]
+    fn primitive_test() {
+        let check = |s: &str| s.parse::<Polynomial>().unwrap().is_primitive().unwrap();
+        assert!(check("x^64 + x^63 + x^61 + x^60 + 1"));
+        assert!(check("0xB400"));
+        assert!(check("x^31 + x^3 + 1"));
+        // Irreducible, but the order of x is 5
+        assert!(!check("x^4 + x^3 + x^2 + x + 1"));
+        // x^2 + x + 1 squared
+        assert!(!check("x^4 + x^2 + 1"));
+        assert!(!check("x^64 + x + 1"));
+
+        let reducible: Polynomial = "x^4 + x^2 + 1".parse().unwrap();
+        assert!(!reducible.is_irreducible().unwrap());
+        assert_eq!(Lfsr::with_primitive_polynomial(&reducible, 1).unwrap_err(), Error::NotPrimitive);
+        let long: Polynomial = "x^127 + x + 1".parse().unwrap();
+        assert_eq!(long.is_primitive(), Err(Error::InvalidLength(127)));
+    }
+
+    #[test]
+    fn brute_force_test() {
+        // Primitive polynomials are exactly the ones giving the maximal period
+        for n in 2..=10u8 {
+            for middle in 0..1u64 << (n - 1) {
+                let mask = 1 << (n - 1) | middle;
+                let Ok(poly) = Polynomial::from_mask(mask) else { continue };
+                let mut lfsr = Lfsr::with_polynomial(&poly, 1).unwrap();
+                assert_eq!(poly.is_primitive().unwrap(), period(&mut lfsr) == (1 << n) - 1, "{}", poly);
+            }
+        }
+    }
+}
@@ -0,0 +1,423 @@
+//! Statistical tests for randomness from NIST SP 800-22 rev. 1a.
+//!
+//! Each test computes a p-value, the probability that a truly random sequence
+//! gives a statistic at least this extreme. Sequences with p-values below
+//! `SIGNIFICANCE` are considered non-random. The tests are meant for sequences
+//! of about a million bits, shorter ones only catch gross defects.
+
+use std::f64::consts::{LN_2, PI};
+use std::fmt;
+
+use crate::{berlekamp_massey, bits_from_bytes, Error};
+
+/// Significance level recommended by SP 800-22
+pub const SIGNIFICANCE: f64 = 0.01;
+
+/// Longest run of ones test parameters for sequences of at least the given number of bits:
+/// block size, run length of the first category and category probabilities
+const LONGEST_RUN: [(usize, usize, usize, &[f64]); 3] = [
+    (750_000, 10_000, 10, &[0.0882, 0.2092, 0.2483, 0.1933, 0.1208, 0.0675, 0.0727]),
+    (6272, 128, 4, &[0.1174, 0.2430, 0.2493, 0.1752, 0.1027, 0.1124]),
+    (128, 8, 1, &[0.2148, 0.3672, 0.2305, 0.1875]),
+];
+
+/// Probabilities of the linear complexity categories, from `T <= -2.5` to `T > 2.5`
+const LINEAR_COMPLEXITY: [f64; 7] = [0.010417, 0.03125, 0.125, 0.5, 0.25, 0.0625, 0.020833];
+
+/// `ln Γ(x)` for `x >= 0.5`, Lanczos approximation with `g = 7`
+fn ln_gamma(x: f64) -> f64 {
+    const COEFFICIENTS: [f64; 8] = [
+        676.5203681218851, -1259.1392167224028, 771.323_428_777_653_1, -176.615_029_162_140_6,
+        12.507343278686905, -0.13857109526572012, 9.984_369_578_019_572e-6, 1.5056327351493116e-7,
+    ];
+    let x = x - 1.0;
+    let a = COEFFICIENTS.iter().enumerate().fold(0.999_999_999_999_809_9, |acc, (i, c)| acc + c / (x + i as f64 + 1.0));
+    let t = x + 7.5;
+    0.5 * (2.0 * PI).ln() + (x + 0.5) * t.ln() - t + a.ln()
+}
+
+/// Regularized lower incomplete gamma function `P(a, x)` by its power series
+fn igam(a: f64, x: f64) -> f64 {
+    if x <= 0.0 { return 0.0; }
+    let (mut sum, mut term, mut r) = (1.0, 1.0, a);
+    while term > sum * f64::EPSILON {
+        r += 1.0;
+        term *= x / r;
+        sum += term;
+    }
+    sum * (a * x.ln() - x - ln_gamma(a)).exp() / a
+}
+
+/// Regularized upper incomplete gamma function `Q(a, x) = 1 - P(a, x)`,
+/// by its continued fraction where the series converges slowly
+fn igamc(a: f64, x: f64) -> f64 {
+    if x < 1.0 || x < a { return 1.0 - igam(a, x); }
+    let factor = (a * x.ln() - x - ln_gamma(a)).exp();
+    if factor == 0.0 { return 0.0; }
+    let (mut y, mut z, mut c) = (1.0 - a, x + 2.0 - a, 0.0);
+    let (mut p_prev, mut q_prev, mut p, mut q) = (1.0, x, x + 1.0, z * x);
+    let mut result = p / q;
+    loop {
+        c += 1.0;
+        y += 1.0;
+        z += 2.0;
+        let (p_next, q_next) = (p * z - p_prev * y * c, q * z - q_prev * y * c);
+        (p_prev, q_prev, p, q) = (p, q, p_next, q_next);
+        if q != 0.0 {
+            let next = p / q;
+            let done = ((result - next) / next).abs() <= f64::EPSILON;
+            result = next;
+            if done { break; }
+        }
+        if p.abs() > 1e300 {
+            (p_prev, q_prev, p, q) = (p_prev * 1e-300, q_prev * 1e-300, p * 1e-300, q * 1e-300);
+        }
+    }
+    result * factor
+}
+
+/// Complementary error function, for `x >= 0`
+pub(crate) fn erfc(x: f64) -> f64 {
+    igamc(0.5, x * x)
+}
+
+/// Standard normal cumulative distribution function
+fn normal_cdf(z: f64) -> f64 {
+    if z >= 0.0 { 1.0 - 0.5 * erfc(z / 2f64.sqrt()) } else { 0.5 * erfc(-z / 2f64.sqrt()) }
+}
+
+/// Chi-squared statistic of observed `counts` against `probabilities` over `total` samples
+fn chi_squared(counts: &[usize], probabilities: &[f64], total: usize) -> f64 {
+    counts.iter().zip(probabilities)
+        .map(|(&v, &p)| (v as f64 - total as f64 * p).powi(2) / (total as f64 * p))
+        .sum()
+}
+
+/// Tests from NIST SP 800-22 on a bit sequence, each returns the p-value.
+///
+/// `report` runs all of them with the recommended parameters, individual tests
+/// return `Error::KeystreamTooShort` when the sequence is too short for the
+/// statistic and `Error::InvalidLength` for unusable block or pattern lengths.
+#[derive(Clone, Debug)]
+pub struct Randomness {
+    bits: Vec<u8>,
+}
+
+impl Randomness {
+    /// Sequence from bytes, most significant bit first like `Keystream` packs them
+    pub fn from_bytes(bytes: &[u8]) -> Self {
+        Randomness { bits: bits_from_bytes(bytes) }
+    }
+
+    /// Sequence of bits, each 0 or 1
+    pub fn from_bits(bits: &[u8]) -> Self {
+        Randomness { bits: bits.to_vec() }
+    }
+
+    pub fn len(&self) -> usize {
+        self.bits.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bits.is_empty()
+    }
+
+    fn require(&self, bits: usize) -> Result<(), Error> {
+        if self.bits.len() < bits { return Err(Error::KeystreamTooShort(bits)); }
+        Ok(())
+    }
+
+    /// Frequency (monobit) test: proportion of ones is close to 1/2
+    pub fn frequency(&self) -> Result<f64, Error> {
+        self.require(1)?;
+        let sum: i64 = self.bits.iter().map(|&b| 2 * b as i64 - 1).sum();
+        Ok(erfc(sum.unsigned_abs() as f64 / (2.0 * self.len() as f64).sqrt()))
+    }
+
+    /// Frequency test within blocks of `block` bits
+    pub fn block_frequency(&self, block: usize) -> Result<f64, Error> {
+        if block == 0 { return Err(Error::InvalidLength(0)); }
+        self.require(block)?;
+        let blocks = self.len() / block;
+        let chi = 4.0 * block as f64 * self.bits.chunks_exact(block)
+            .map(|b| (b.iter().map(|&b| b as f64).sum::<f64>() / block as f64 - 0.5).powi(2))
+            .sum::<f64>();
+        Ok(igamc(blocks as f64 / 2.0, chi / 2.0))
+    }
+
+    /// Runs test: number of runs of identical bits, that is how fast the bits oscillate.
+    /// Gives 0 when the sequence fails the frequency prerequisite.
+    pub fn runs(&self) -> Result<f64, Error> {
+        self.require(1)?;
+        let n = self.len() as f64;
+        let pi = self.bits.iter().filter(|&&b| b == 1).count() as f64 / n;
+        if (pi - 0.5).abs() >= 2.0 / n.sqrt() { return Ok(0.0); }
+        let runs = 1 + self.bits.windows(2).filter(|w| w[0] != w[1]).count();
+        let expected = 2.0 * n * pi * (1.0 - pi);
+        Ok(erfc((runs as f64 - expected).abs() / (2.0 * (2.0 * n).sqrt() * pi * (1.0 - pi))))
+    }
+
+    /// Longest run of ones in blocks of 8, 128 or 10000 bits, depending on the length
+    pub fn longest_run(&self) -> Result<f64, Error> {
+        self.require(128)?;
+        let &(_, block, lowest, probabilities) = LONGEST_RUN.iter().find(|t| self.len() >= t.0).unwrap();
+        let categories = probabilities.len();
+        let mut counts = vec![0; categories];
+        for b in self.bits.chunks_exact(block) {
+            let (longest, _) = b.iter().fold((0, 0), |(longest, run), &bit| {
+                let run = if bit == 1 { run + 1 } else { 0 };
+                (longest.max(run), run)
+            });
+            counts[longest.clamp(lowest, lowest + categories - 1) - lowest] += 1;
+        }
+        let chi = chi_squared(&counts, probabilities, self.len() / block);
+        Ok(igamc((categories - 1) as f64 / 2.0, chi / 2.0))
+    }
+
+    /// Counts of all overlapping `m` bit patterns, the sequence wrapping around
+    fn pattern_counts(&self, m: u32) -> Vec<usize> {
+        let mut counts = vec![0; 1 << m];
+        if m == 0 { return counts; }
+        let mask = (1 << m) - 1;
+        let mut pattern = self.bits[..m as usize - 1].iter().fold(0, |acc, &b| acc << 1 | b as usize);
+        for &b in self.bits[m as usize - 1..].iter().chain(&self.bits[..m as usize - 1]) {
+            pattern = (pattern << 1 | b as usize) & mask;
+            counts[pattern] += 1;
+        }
+        counts
+    }
+
+    /// `psi^2_m` statistic of the serial test
+    fn psi_squared(&self, m: u32) -> f64 {
+        if m == 0 { return 0.0; }
+        let n = self.len() as f64;
+        let squares: f64 = self.pattern_counts(m).iter().map(|&c| (c * c) as f64).sum();
+        (1u64 << m) as f64 / n * squares - n
+    }
+
+    /// Serial test: all overlapping `m` bit patterns are equally likely, `m` from 2 to 24.
+    /// Returns both p-values, from the first and the second differences of `psi^2`.
+    pub fn serial(&self, m: u32) -> Result<(f64, f64), Error> {
+        if !(2..=24).contains(&m) { return Err(Error::InvalidLength(m)); }
+        self.require(m as usize)?;
+        let (psi, psi1, psi2) = (self.psi_squared(m), self.psi_squared(m - 1), self.psi_squared(m - 2));
+        let p1 = igamc((1u64 << (m - 2)) as f64, (psi - psi1) / 2.0);
+        let p2 = igamc((1u64 << m) as f64 / 8.0, (psi - 2.0 * psi1 + psi2) / 2.0);
+        Ok((p1, p2))
+    }
+
+    /// `phi(m)` of the approximate entropy test
+    fn phi(&self, m: u32) -> f64 {
+        let n = self.len() as f64;
+        self.pattern_counts(m).iter().filter(|&&c| c > 0).map(|&c| c as f64 / n * (c as f64 / n).ln()).sum()
+    }
+
+    /// Approximate entropy test: frequencies of overlapping `m` and `m + 1` bit patterns, `m` from 1 to 23
+    pub fn approximate_entropy(&self, m: u32) -> Result<f64, Error> {
+        if !(1..=23).contains(&m) { return Err(Error::InvalidLength(m)); }
+        self.require(m as usize + 1)?;
+        let entropy = self.phi(m) - self.phi(m + 1);
+        let chi = 2.0 * self.len() as f64 * (LN_2 - entropy);
+        Ok(igamc((1u64 << (m - 1)) as f64, chi / 2.0))
+    }
+
+    /// Cumulative sums test: maximal excursion of the random walk of `±1` steps,
+    /// from the start or, if `backward`, from the end of the sequence
+    pub fn cumulative_sums(&self, backward: bool) -> Result<f64, Error> {
+        self.require(1)?;
+        let steps = self.bits.iter().map(|&b| 2 * b as i64 - 1);
+        let walk = |(sum, max): (i64, u64), step: i64| (sum + step, max.max((sum + step).unsigned_abs()));
+        let (_, z) = if backward { steps.rev().fold((0, 0), walk) } else { steps.fold((0, 0), walk) };
+        let (n, z) = (self.len() as f64, z as f64);
+        let term = |k: i64, a: i64, b: i64| normal_cdf((4 * k + a) as f64 * z / n.sqrt()) - normal_cdf((4 * k + b) as f64 * z / n.sqrt());
+        let k_max = ((n / z - 1.0) / 4.0).floor() as i64;
+        let first: f64 = ((((-n / z + 1.0) / 4.0).floor() as i64)..=k_max).map(|k| term(k, 1, -1)).sum();
+        let second: f64 = ((((-n / z - 3.0) / 4.0).floor() as i64)..=k_max).map(|k| term(k, 3, 1)).sum();
+        Ok(1.0 - first + second)
+    }
+
+    /// Linear complexity test: linear complexity of blocks of `block` bits, found by
+    /// `berlekamp_massey`, is close to that of random blocks
+    pub fn linear_complexity(&self, block: usize) -> Result<f64, Error> {
+        if block == 0 { return Err(Error::InvalidLength(0)); }
+        self.require(block)?;
+        let m = block as f64;
+        let sign = if block.is_multiple_of(2) { 1.0 } else { -1.0 };
+        let mean = m / 2.0 + (9.0 - sign) / 36.0 - (m / 3.0 + 2.0 / 9.0) * 0.5f64.powi(block.min(1100) as i32);
+        let mut counts = [0; 7];
+        for b in self.bits.chunks_exact(block) {
+            let t = sign * (berlekamp_massey(b).length as f64 - mean) + 2.0 / 9.0;
+            counts[((t + 3.5).ceil() as usize).clamp(1, 7) - 1] += 1;
+        }
+        let chi = chi_squared(&counts, &LINEAR_COMPLEXITY, self.len() / block);
+        Ok(igamc(3.0, chi / 2.0))
+    }
+
+    /// Runs all tests with parameters recommended by SP 800-22 for the length of the sequence
+    pub fn report(&self) -> RandomnessReport {
+        let log_n = self.len().max(1).ilog2();
+        let serial_m = log_n.saturating_sub(3).clamp(2, 16);
+        let entropy_m = log_n.saturating_sub(6).clamp(1, 10);
+        let serial = self.serial(serial_m);
+        let results = vec![
+            ("Frequency".to_owned(), self.frequency()),
+            ("Block frequency (M = 128)".to_owned(), self.block_frequency(128)),
+            ("Runs".to_owned(), self.runs()),
+            ("Longest run of ones".to_owned(), self.longest_run()),
+            (format!("Serial (m = {}) first", serial_m), serial.clone().map(|p| p.0)),
+            (format!("Serial (m = {}) second", serial_m), serial.map(|p| p.1)),
+            (format!("Approximate entropy (m = {})", entropy_m), self.approximate_entropy(entropy_m)),
+            ("Cumulative sums forward".to_owned(), self.cumulative_sums(false)),
+            ("Cumulative sums backward".to_owned(), self.cumulative_sums(true)),
+            ("Linear complexity (M = 500)".to_owned(), self.linear_complexity(500)),
+        ];
+        RandomnessReport { bits: self.len(), results }
+    }
+}
+
+/// P-values of all tests made by `Randomness::report`
+#[derive(Clone, Debug)]
+pub struct RandomnessReport {
+    /// Length of the tested sequence
+    pub bits: usize,
+    /// Name of each test with its p-value, or the reason it couldn't run
+    pub results: Vec<(String, Result<f64, Error>)>,
+}
+
+impl RandomnessReport {
+    /// Whether every test ran and has p-value of at least `SIGNIFICANCE`,
+    /// a sequence too short for some of the tests doesn't pass
+    pub fn passed(&self) -> bool {
+        self.results.iter().all(|(_, p)| p.as_ref().is_ok_and(|&p| p >= SIGNIFICANCE))
+    }
+
+    /// Number of tests that couldn't run
+    pub fn skipped(&self) -> usize {
+        self.results.iter().filter(|(_, p)| p.is_err()).count()
+    }
+}
+
+impl fmt::Display for RandomnessReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{} bits, significance level {}", self.bits, SIGNIFICANCE)?;
+        for (name, p) in &self.results {
+            match p {
+                Ok(p) => writeln!(f, "{:<32} {:.6}  {}", name, p, if *p >= SIGNIFICANCE { "PASS" } else { "FAIL" })?,
+                Err(e) => writeln!(f, "{:<32} skipped, {}", name, e)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{primitive_polynomial, Keystream, Lfsr, Trivium};
+
+    fn sequence(s: &str) -> Randomness {
+        Randomness::from_bits(&s.bytes().map(|c| c - b'0').collect::<Vec<_>>())
+    }
+
+    fn assert_close(p: f64, expected: f64) {
+        assert!((p - expected).abs() < 1e-6, "{} != {}", p, expected);
+    }
+
+    /// First 100 bits of the binary expansion of pi, used by the examples of SP 800-22
+    const PI_100: &str = "1100100100001111110110101010001000100001011010001100001000110100110001001100011001100010100010111000";
+
+    #[test]
+    fn special_functions_test() {
+        assert_close(ln_gamma(0.5), PI.sqrt().ln());
+        assert_close(ln_gamma(10.0), 362880f64.ln());
+        assert_close(erfc(1.0), 0.157299207050285);
+        assert_close(igamc(3.0, 2.0), 5.0 * (-2.0f64).exp());
+        assert_close(igamc(3.0, 20.0), 221.0 * (-20.0f64).exp());
+        assert_close(normal_cdf(-1.0), 0.158655253931457);
+        assert_eq!(igamc(4.0, 1e6), 0.0);
+    }
+
+    #[test]
+    fn spec_examples_test() {
+        // Worked examples of SP 800-22 section 2
+        assert_close(sequence("1011010101").frequency().unwrap(), 0.527089);
+        assert_close(sequence(PI_100).frequency().unwrap(), 0.109599);
+        assert_close(sequence("0110011010").block_frequency(3).unwrap(), 0.801252);
+        assert_close(sequence(PI_100).block_frequency(10).unwrap(), 0.706438);
+        assert_close(sequence("1001101011").runs().unwrap(), 0.147232);
+        assert_close(sequence(PI_100).runs().unwrap(), 0.500798);
+        let longest = sequence("11001100000101010110110001001100111000000000001001001101010100010001001111010110100000001101011111001100111001101101100010110010");
+        assert!((longest.longest_run().unwrap() - 0.180598).abs() < 1e-4);
+        let (p1, p2) = sequence("0011011101").serial(3).unwrap();
+        assert_close(p1, 0.808792);
+        assert_close(p2, 0.670320);
+        assert_close(sequence("0100110101").approximate_entropy(3).unwrap(), 0.261961);
+        // Longest run and this example round intermediate values, hence the tolerance
+        assert!((sequence("1011010111").cumulative_sums(false).unwrap() - 0.4116588).abs() < 1e-4);
+        assert_close(sequence(PI_100).cumulative_sums(false).unwrap(), 0.219194);
+        assert_close(sequence(PI_100).cumulative_sums(true).unwrap(), 0.114866);
+    }
+
+    #[test]
+    fn e_example_test() {
+        // First million bits of e, "10" followed by the fraction, as in the data.e file of SP 800-22
+        let e = Randomness::from_bytes(include_bytes!("../testdata/e.bin"));
+        // Example of section 2.10.8, its block counts 11, 31, 116, 501, 258, 57, 26 give
+        // chi-squared 2.706147 rather than the printed 2.700348, hence the tolerance
+        assert!((e.linear_complexity(1000).unwrap() - 0.845406).abs() < 1e-3);
+        // Results for e from appendix B
+        assert_close(e.frequency().unwrap(), 0.953749);
+        assert_close(e.block_frequency(128).unwrap(), 0.211072);
+        assert_close(e.runs().unwrap(), 0.561917);
+        assert_close(e.longest_run().unwrap(), 0.718945);
+        assert_close(e.approximate_entropy(10).unwrap(), 0.700073);
+        let (p1, p2) = e.serial(2).unwrap();
+        assert_close(p1, 0.843764);
+        assert_close(p2, 0.561915);
+        assert_close(e.cumulative_sums(false).unwrap(), 0.669886);
+        assert_close(e.cumulative_sums(true).unwrap(), 0.724265);
+    }
+
+    #[test]
+    fn parameters_test() {
+        let short = sequence("0110");
+        assert_eq!(short.longest_run().unwrap_err(), Error::KeystreamTooShort(128));
+        assert_eq!(short.block_frequency(0).unwrap_err(), Error::InvalidLength(0));
+        assert_eq!(short.linear_complexity(500).unwrap_err(), Error::KeystreamTooShort(500));
+        assert_eq!(short.serial(25).unwrap_err(), Error::InvalidLength(25));
+        assert_eq!(Randomness::from_bytes(&[]).frequency().unwrap_err(), Error::KeystreamTooShort(1));
+        let report = short.report();
+        assert!(report.results.iter().all(|(_, p)| p.as_ref().map_or(true, |&p| p >= SIGNIFICANCE)));
+        assert!(report.skipped() > 0);
+        assert!(!report.passed());
+    }
+
+    #[test]
+    fn good_generator_test() {
+        let mut bytes = vec![0u8; 1 << 17];
+        Trivium::new(b"0123456789", b"abcdefghij").fill_bytes(&mut bytes);
+        let report = Randomness::from_bytes(&bytes).report();
+        assert_eq!(report.skipped(), 0);
+        assert!(report.passed(), "{}", report);
+    }
+
+    #[test]
+    fn bad_generators_test() {
+        let zeros = Randomness::from_bytes(&[0; 1 << 14]);
+        assert!(!zeros.report().passed());
+        assert_eq!(zeros.frequency().unwrap(), 0.0);
+
+        // Balanced, but oscillates too fast
+        let alternating = Randomness::from_bytes(&[0x55; 1 << 14]);
+        assert_eq!(alternating.frequency().unwrap(), 1.0);
+        assert!(alternating.runs().unwrap() < SIGNIFICANCE);
+        assert!(alternating.serial(8).unwrap().0 < SIGNIFICANCE);
+
+        // Short register passes frequency tests, but its linear complexity is just 32
+        let mut bytes = vec![0u8; 1 << 15];
+        Lfsr::with_polynomial(&primitive_polynomial(32).unwrap(), 0x1234_5678).unwrap().fill_bytes(&mut bytes);
+        let lfsr = Randomness::from_bytes(&bytes);
+        assert!(lfsr.frequency().unwrap() >= SIGNIFICANCE);
+        assert!(lfsr.linear_complexity(500).unwrap() < 1e-6);
+    }
+}
@@ -0,0 +1,85 @@
+//! `rand_core` traits for `Lfsr`, enabled by the `rng` feature.
+//!
+//! This makes a register usable as a fast, reproducible source of test data
+//! wherever `rand` generators are accepted. It is NOT cryptographically secure:
+//! output is linear, 128 bits of it reveal the whole register (see `berlekamp_massey`).
+
+use rand_core::{RngCore, SeedableRng};
+
+use crate::{primitive_polynomial, Keystream, Lfsr};
+
+/// Replaces the all zero seed, which would keep the register at 0 forever
+const ZERO_SEED_REPLACEMENT: u64 = 0x9E37_79B9_7F4A_7C15;
+
+impl RngCore for Lfsr {
+    /// Next 32 output bits, the first one in the lowest bit
+    fn next_u32(&mut self) -> u32 {
+        self.next_bits(32) as u32
+    }
+
+    /// Next 64 output bits, the first one in the lowest bit
+    fn next_u64(&mut self) -> u64 {
+        self.next_bits(64)
+    }
+
+    /// Same bytes as `Keystream::fill_bytes`
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        Keystream::fill_bytes(self, dest);
+    }
+}
+
+impl SeedableRng for Lfsr {
+    type Seed = [u8; 8];
+
+    /// 64 bit register with the primitive polynomial from `primitive_polynomial(64)`,
+    /// initialized with the little endian `seed`
+    fn from_seed(seed: Self::Seed) -> Self {
+        let init = match u64::from_le_bytes(seed) {
+            0 => ZERO_SEED_REPLACEMENT,
+            init => init,
+        };
+        Lfsr::with_polynomial(&primitive_polynomial(64).unwrap(), init).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn determinism_test() {
+        for seed in [0u64, 1, 42, 0xDEAD_BEEF, u64::MAX] {
+            let mut a = Lfsr::seed_from_u64(seed);
+            let mut b = Lfsr::seed_from_u64(seed);
+            let (mut bytes_a, mut bytes_b) = ([0u8; 100], [0u8; 100]);
+            RngCore::fill_bytes(&mut a, &mut bytes_a);
+            RngCore::fill_bytes(&mut b, &mut bytes_b);
+            assert_eq!(bytes_a, bytes_b);
+            assert_eq!(a.next_u64(), b.next_u64());
+            assert_eq!(a.next_u32(), b.next_u32());
+        }
+        let outputs: Vec<u64> = (0..10).map(|seed| Lfsr::seed_from_u64(seed).next_u64()).collect();
+        assert!(outputs.iter().enumerate().all(|(i, o)| !outputs[..i].contains(o)));
+    }
+
+    #[test]
+    fn from_seed_test() {
+        let mut rng = Lfsr::from_seed(0x0123_4567_89AB_CDEFu64.to_le_bytes());
+        assert_eq!(rng.state(), 0x0123_4567_89AB_CDEF);
+        assert_eq!(rng.polynomial(), Ok(primitive_polynomial(64).unwrap()));
+
+        let mut copy = rng.clone();
+        let low = copy.next_bits(32);
+        let high = copy.next_bits(32);
+        assert_eq!(rng.next_u64(), high << 32 | low);
+        assert_eq!(rng.next_u32() as u64, copy.next_bits(32));
+
+        let mut bytes = [0u8; 9];
+        RngCore::fill_bytes(&mut rng, &mut bytes);
+        let mut expected = [0u8; 9];
+        Keystream::fill_bytes(&mut copy, &mut expected);
+        assert_eq!(bytes, expected);
+
+        assert_eq!(Lfsr::from_seed([0; 8]).state(), ZERO_SEED_REPLACEMENT);
+    }
+}
@@ -0,0 +1,149 @@
+use std::io::{self, Read, Seek, SeekFrom};
+
+use crate::{gf2, Keystream, Lfsr};
+
+impl Lfsr {
+    /// Moves the register `n` steps forward, same as discarding `n` output bits,
+    /// with O(log n) multiplications modulo the characteristic polynomial
+    pub fn advance(&mut self, n: u128) {
+        // Output satisfies s[t+length] = sum(s[t+tap]), so x^(n+i) reduced modulo
+        // x^length + sum(x^tap) gives bit i of the new state as a sum of current bits
+        let len = self.bit_len as u32;
+        let charpoly = self.taps.iter().fold(1u128 << len, |p, &tap| p | 1 << tap);
+        let mut power = gf2::pow_mod(0b10, n, charpoly);
+        let mut reg = 0u64;
+        for i in 0..len {
+            reg |= (((power as u64 & self.reg).count_ones() & 1) as u64) << i;
+            power = gf2::rem(power << 1, charpoly);
+        }
+        self.reg = reg;
+    }
+}
+
+/// Keystream of a register as a `Read` + `Seek` byte stream.
+///
+/// Seeking jumps the register with `Lfsr::advance`, so reading a range in the
+/// middle of the keystream doesn't need generating everything before it.
+/// The stream is endless, so seeking from the end is not supported.
+#[derive(Clone, Debug)]
+pub struct KeystreamReader {
+    start: Lfsr,
+    lfsr: Lfsr,
+    pos: u64,
+}
+
+impl KeystreamReader {
+    /// Reader positioned at the start of the keystream of `lfsr`
+    pub fn new(lfsr: Lfsr) -> Self {
+        KeystreamReader {
+            start: lfsr.clone(),
+            lfsr,
+            pos: 0,
+        }
+    }
+
+    /// Position in bytes from the start of the keystream
+    pub fn position(&self) -> u64 {
+        self.pos
+    }
+
+    /// Xors `data` with the keystream from the current position, same as `Keystream::apply_keystream`
+    pub fn apply_keystream(&mut self, data: &mut [u8]) {
+        self.lfsr.apply_keystream(data);
+        self.pos += data.len() as u64;
+    }
+}
+
+impl Read for KeystreamReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.lfsr.fill_bytes(buf);
+        self.pos += buf.len() as u64;
+        Ok(buf.len())
+    }
+}
+
+impl Seek for KeystreamReader {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let new_pos = match pos {
+            SeekFrom::Start(p) => Some(p),
+            SeekFrom::Current(d) => self.pos.checked_add_signed(d),
+            SeekFrom::End(_) => return Err(io::Error::new(io::ErrorKind::Unsupported, "keystream has no end")),
+        };
+        let new_pos = new_pos.ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "invalid seek position"))?;
+        if new_pos < self.pos {
+            self.lfsr = self.start.clone();
+            self.pos = 0;
+        }
+        self.lfsr.advance(8 * (new_pos - self.pos) as u128);
+        self.pos = new_pos;
+        Ok(new_pos)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{primitive_polynomial, xor_stream};
+
+    #[test]
+    fn advance_test() {
+        let configs: [(&[u8], u64, u8); 4] = [
+            (&[0, 1], 0b0001, 4),
+            (&[1, 2], 0b10111, 5),
+            (&[0, 1, 3, 4], 0xABCDABCD_ABCDABCD, 64),
+            (&[0, 5, 17, 30], 0x1234_5678, 31),
+        ];
+        for (taps, init, length) in configs {
+            for n in [0u128, 1, 2, 7, 63, 64, 65, 1000, 4099] {
+                let mut naive = Lfsr::new(taps, init, length).unwrap();
+                (0..n).for_each(|_| { naive.next_bit(); });
+                let mut jumped = Lfsr::new(taps, init, length).unwrap();
+                jumped.advance(n);
+                assert_eq!(jumped.state(), naive.state(), "taps {:?}, n {}", taps, n);
+            }
+        }
+    }
+
+    #[test]
+    fn advance_period_test() {
+        let poly = primitive_polynomial(64).unwrap();
+        let mut lfsr = Lfsr::with_polynomial(&poly, 0x0123_4567_89AB_CDEF).unwrap();
+        lfsr.advance(u64::MAX as u128);
+        assert_eq!(lfsr.state(), 0x0123_4567_89AB_CDEF);
+        lfsr.advance(3 * u64::MAX as u128 + 5);
+        let mut naive = Lfsr::with_polynomial(&poly, 0x0123_4567_89AB_CDEF).unwrap();
+        (0..5).for_each(|_| { naive.next_bit(); });
+        assert_eq!(lfsr.state(), naive.state());
+    }
+
+    #[test]
+    fn reader_seek_test() {
+        let lfsr = Lfsr::new(&[0, 1, 3, 4], 0x1234, 64).unwrap();
+        let mut keystream = vec![0u8; 5000];
+        lfsr.clone().fill_bytes(&mut keystream);
+
+        let mut reader = KeystreamReader::new(lfsr.clone());
+        let mut buf = [0u8; 100];
+        assert_eq!(reader.seek(SeekFrom::Start(3000)).unwrap(), 3000);
+        reader.read_exact(&mut buf).unwrap();
+        assert_eq!(buf, keystream[3000..3100]);
+        assert_eq!(reader.seek(SeekFrom::Current(-1050)).unwrap(), 2050);
+        reader.read_exact(&mut buf).unwrap();
+        assert_eq!(buf, keystream[2050..2150]);
+        assert_eq!(reader.seek(SeekFrom::Current(1)).unwrap(), 2151);
+        reader.read_exact(&mut buf).unwrap();
+        assert_eq!(buf, keystream[2151..2251]);
+        assert!(reader.seek(SeekFrom::End(0)).is_err());
+        assert!(reader.seek(SeekFrom::Current(-3000)).is_err());
+
+        // Decrypt a range in the middle of an encrypted file
+        let plain: Vec<u8> = (0..5000u32).map(|i| (i * 31) as u8).collect();
+        let mut encrypted = Vec::new();
+        xor_stream(&mut plain.as_slice(), &mut encrypted, &mut lfsr.clone()).unwrap();
+        let mut part = encrypted[4000..4500].to_vec();
+        reader.seek(SeekFrom::Start(4000)).unwrap();
+        reader.apply_keystream(&mut part);
+        assert_eq!(part, plain[4000..4500]);
+        assert_eq!(reader.position(), 4500);
+    }
+}
@@ -0,0 +1,115 @@
+use crate::nfsr::{state_bit, Nfsr};
+use crate::Keystream;
+
+/// Trivium stream cipher from eSTREAM: three nonlinear registers of 93, 84 and 111 bits.
+///
+/// The spec numbers the 288 state bits `s1..s288` from the input end, here each register
+/// keeps its output end at bit 0, so `s_k` of a register of length `n` starting after
+/// `s_m` is its bit `n - (k - m)`.
+#[derive(Clone, Debug)]
+pub struct Trivium {
+    a: Nfsr,
+    b: Nfsr,
+    c: Nfsr,
+}
+
+/// Bits of a register from bytes loaded as the eSTREAM reference code does: `bytes[i / 8] >> (i % 8)`
+/// is `K_(80 - i)` of the spec, so the last bit of the last byte is on top
+fn load(bytes: &[u8], length: u32) -> Vec<u64> {
+    let mut words = vec![0u64; length.div_ceil(64) as usize];
+    for i in 0..8 * bytes.len() as u32 {
+        let pos = length - 8 * bytes.len() as u32 + i;
+        words[pos as usize / 64] |= ((bytes[i as usize / 8] >> (i % 8) & 1) as u64) << (pos % 64);
+    }
+    words
+}
+
+impl Trivium {
+    /// Loads 80 bit `key` and `iv` and runs 1152 initialization clocks
+    pub fn new(key: &[u8; 10], iv: &[u8; 10]) -> Self {
+        // Each register's own feedback is linear, s69, s171 and s264,
+        // the nonlinear part comes from the previous register as input
+        fn s69(s: &[u64]) -> u8 { state_bit(s, 24) }
+        fn s171(s: &[u64]) -> u8 { state_bit(s, 6) }
+        fn s264(s: &[u64]) -> u8 { state_bit(s, 24) }
+        let register = |feedback: fn(&[u64]) -> u8, init: &[u64], length| Nfsr::new(feedback, init, length).unwrap();
+        let mut trivium = Trivium {
+            a: register(s69, &load(key, 93), 93),
+            b: register(s171, &load(iv, 84), 84),
+            c: register(s264, &[0b111], 111),
+        };
+        (0..4 * 288).for_each(|_| { trivium.next_bit(); });
+        trivium
+    }
+}
+
+impl Keystream for Trivium {
+    fn next_bit(&mut self) -> u8 {
+        // t1 = s66 + s93, t2 = s162 + s177, t3 = s243 + s288
+        let t1 = self.a.bit(27) ^ self.a.bit(0);
+        let t2 = self.b.bit(15) ^ self.b.bit(0);
+        let t3 = self.c.bit(45) ^ self.c.bit(0);
+        // s91*s92, s175*s176, s286*s287
+        let t1_and = self.a.bit(2) & self.a.bit(1);
+        let t2_and = self.b.bit(2) & self.b.bit(1);
+        let t3_and = self.c.bit(2) & self.c.bit(1);
+        self.a.clock_with_input(t3 ^ t3_and);
+        self.b.clock_with_input(t1 ^ t1_and);
+        self.c.clock_with_input(t2 ^ t2_and);
+        t1 ^ t2 ^ t3
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// eSTREAM vectors pack keystream bits least significant first
+    fn estream_bytes(trivium: &mut Trivium, count: usize) -> Vec<u8> {
+        (0..count).map(|_| (0..8).fold(0u8, |acc, i| acc | trivium.next_bit() << i)).collect()
+    }
+
+    #[test]
+    fn estream_vector_test() {
+        // Key and IV all zero, first 64 bytes of the eSTREAM test vectors
+        let expected = [
+            0xFB, 0xE0, 0xBF, 0x26, 0x58, 0x59, 0x05, 0x1B, 0x51, 0x7A, 0x2E, 0x4E, 0x23, 0x9F, 0xC9, 0x7F,
+            0x56, 0x32, 0x03, 0x16, 0x19, 0x07, 0xCF, 0x2D, 0xE7, 0xA8, 0x79, 0x0F, 0xA1, 0xB2, 0xE9, 0xCD,
+            0xF7, 0x52, 0x92, 0x03, 0x02, 0x68, 0xB7, 0x38, 0x2B, 0x4C, 0x1A, 0x75, 0x9A, 0xA2, 0x59, 0x9A,
+            0x28, 0x55, 0x49, 0x98, 0x6E, 0x74, 0x80, 0x59, 0x03, 0x80, 0x1A, 0x4C, 0xB5, 0xA5, 0xD4, 0xF2,
+        ];
+        let mut trivium = Trivium::new(&[0; 10], &[0; 10]);
+        assert_eq!(estream_bytes(&mut trivium, 64), expected);
+
+        // Set 1, vector 0: key 80 00 .. 00, IV all zero
+        let expected = [
+            0x38, 0xEB, 0x86, 0xFF, 0x73, 0x0D, 0x7A, 0x9C, 0xAF, 0x8D, 0xF1, 0x3A, 0x44, 0x20, 0x54, 0x0D,
+            0xBB, 0x7B, 0x65, 0x14, 0x64, 0xC8, 0x75, 0x01, 0x55, 0x20, 0x41, 0xC2, 0x49, 0xF2, 0x9A, 0x64,
+        ];
+        let mut trivium = Trivium::new(&[0x80, 0, 0, 0, 0, 0, 0, 0, 0, 0], &[0; 10]);
+        assert_eq!(estream_bytes(&mut trivium, 32), expected);
+
+        // Set 6, vector 0
+        let key = [0x00, 0x53, 0xA6, 0xF9, 0x4C, 0x9F, 0xF2, 0x45, 0x98, 0xEB];
+        let iv = [0x0D, 0x74, 0xDB, 0x42, 0xA9, 0x10, 0x77, 0xDE, 0x45, 0xAC];
+        let expected = [
+            0xF4, 0xCD, 0x95, 0x4A, 0x71, 0x7F, 0x26, 0xA7, 0xD6, 0x93, 0x08, 0x30, 0xC4, 0xE7, 0xCF, 0x08,
+            0x19, 0xF8, 0x0E, 0x03, 0xF2, 0x5F, 0x34, 0x2C, 0x64, 0xAD, 0xC6, 0x6A, 0xBA, 0x7F, 0x8A, 0x8E,
+            0x6E, 0xAA, 0x49, 0xF2, 0x36, 0x32, 0xAE, 0x3C, 0xD4, 0x1A, 0x7B, 0xD2, 0x90, 0xA0, 0x13, 0x2F,
+            0x81, 0xC6, 0xD4, 0x04, 0x3B, 0x6E, 0x39, 0x7D, 0x73, 0x88, 0xF3, 0xA0, 0x3B, 0x5F, 0xE3, 0x58,
+        ];
+        let mut trivium = Trivium::new(&key, &iv);
+        assert_eq!(estream_bytes(&mut trivium, 64), expected);
+    }
+
+    #[test]
+    fn key_iv_test() {
+        let mut zero = Trivium::new(&[0; 10], &[0; 10]);
+        let mut key = Trivium::new(&[0x80, 0, 0, 0, 0, 0, 0, 0, 0, 0], &[0; 10]);
+        let mut iv = Trivium::new(&[0; 10], &[0, 0, 0, 0, 0, 0, 0, 0, 0, 1]);
+        let (zero, key, iv) = (estream_bytes(&mut zero, 16), estream_bytes(&mut key, 16), estream_bytes(&mut iv, 16));
+        assert_ne!(zero, key);
+        assert_ne!(zero, iv);
+        assert_ne!(key, iv);
+    }
+}
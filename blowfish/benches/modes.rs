@@ -0,0 +1,58 @@
+//! Throughput of the block modes.
+//! Run with `cargo bench`, input size can be changed with `BENCH_BYTES` (1 GiB by default).
+
+use std::env;
+use std::io::{self, Result};
+use std::thread;
+use std::time::Instant;
+
+use blowfish::blowfish::{bytes_to_u32, encode_block, Keys};
+use blowfish::modes;
+
+const KEY: [u8; 8] = [0x01, 0x23, 0x45, 0x67, 0x89, 0xAB, 0xCD, 0xEF];
+const IV: [u8; 8] = [0xFE, 0xDC, 0xBA, 0x98, 0x76, 0x54, 0x32, 0x10];
+
+fn bench<F: FnMut() -> Result<()>>(name: &str, bytes: usize, mut run: F) {
+    let start = Instant::now();
+    run().unwrap();
+    let secs = start.elapsed().as_secs_f64();
+    println!("{name:<32} {secs:>8.2} s {:>10.1} MiB/s", bytes as f64 / secs / (1 << 20) as f64);
+}
+
+fn main() {
+    let bytes = env::var("BENCH_BYTES").ok()
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(1usize << 30);
+    let threads = thread::available_parallelism().map_or(1, |n| n.get());
+    let data = vec![0x5Au8; bytes];
+    let keys = Keys::new(&KEY);
+
+    println!("{} MiB of input, {} threads", bytes >> 20, threads);
+
+    bench("block by block", bytes, || {
+        let mut acc = 0u32;
+        for block in data.chunks_exact(8) {
+            let (l, r) = encode_block(bytes_to_u32(&block[..4]), bytes_to_u32(&block[4..]), &keys);
+            acc ^= l ^ r;
+        }
+        std::hint::black_box(acc);
+        Ok(())
+    });
+
+    let mut thread_counts = vec![1, threads];
+    thread_counts.dedup();
+    for t in thread_counts {
+        bench(&format!("ecb enc, {t} threads"), bytes,
+            || modes::enc_ecb_mt(&mut data.as_slice(), &KEY, &mut io::sink(), t));
+        bench(&format!("ecb dec, {t} threads"), bytes,
+            || modes::dec_ecb_mt(&mut data.as_slice(), &KEY, &mut io::sink(), t));
+        bench(&format!("ctr, {t} threads"), bytes,
+            || modes::enc_ctr_mt(&mut data.as_slice(), &KEY, &IV, &mut io::sink(), t));
+        bench(&format!("cbc dec, {t} threads"), bytes,
+            || modes::dec_cbc_mt(&mut data.as_slice(), &KEY, &IV, &mut io::sink(), t));
+        bench(&format!("cfb dec, {t} threads"), bytes,
+            || modes::dec_cfb_mt(&mut data.as_slice(), &KEY, &IV, &mut io::sink(), t));
+    }
+    bench("cbc enc", bytes, || modes::enc_cbc(&mut data.as_slice(), &KEY, &IV, &mut io::sink()));
+    bench("cfb enc", bytes, || modes::enc_cfb(&mut data.as_slice(), &KEY, &IV, &mut io::sink()));
+}
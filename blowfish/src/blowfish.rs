@@ -7,34 +7,94 @@ pub fn encode_block(xl: u32, xr: u32, keys: &Keys) -> (u32, u32) {
     let mut xl = xl;
     let mut xr = xr;
     for i in 0usize..16usize {
-        xl = xl ^ keys.parray[i];
-        xr = f(xl, &keys) ^ xr;
+        xl ^= keys.parray[i];
+        xr ^= f(xl, keys);
         mem::swap(&mut xl, &mut xr);
     }
     mem::swap(&mut xl, &mut xr);
     xr ^= keys.parray[16];
     xl ^= keys.parray[17];
-    return (xl, xr);
+    (xl, xr)
 }
 
 fn f(int: u32, keys: &Keys) -> u32 {
+    if cfg!(feature = "constant-time") { f_ct(int, keys) } else { f_fast(int, keys) }
+}
+
+fn f_fast(int: u32, keys: &Keys) -> u32 {
     let bytes = int.to_be_bytes();
     let part = keys.sbox[0][bytes[0] as usize].wrapping_add(keys.sbox[1][bytes[1] as usize]);
-    return (part ^ keys.sbox[2][bytes[2] as usize]).wrapping_add(keys.sbox[3][bytes[3] as usize]);
+    (part ^ keys.sbox[2][bytes[2] as usize]).wrapping_add(keys.sbox[3][bytes[3] as usize])
+}
+
+/// Same as `f_fast`, but reads every S-box entry on each lookup,
+/// so the memory access pattern doesn't depend on secret data.
+fn f_ct(int: u32, keys: &Keys) -> u32 {
+    let bytes = int.to_be_bytes();
+    let part = ct_lookup(&keys.sbox[0], bytes[0]).wrapping_add(ct_lookup(&keys.sbox[1], bytes[1]));
+    (part ^ ct_lookup(&keys.sbox[2], bytes[2])).wrapping_add(ct_lookup(&keys.sbox[3], bytes[3]))
+}
+
+fn ct_lookup(sbox: &[u32; 256], index: u8) -> u32 {
+    sbox.iter().enumerate().fold(0u32, |acc, (i, &entry)| {
+        let diff = i as u32 ^ index as u32;
+        // All ones when diff == 0, zero otherwise
+        let mask = ((diff | diff.wrapping_neg()) >> 31).wrapping_sub(1);
+        acc | (entry & std::hint::black_box(mask))
+    })
 }
 
 pub fn decode_block(xl: u32, xr: u32, keys: &Keys) -> (u32, u32) {
     let mut xl = xl;
     let mut xr = xr;
     for i in (2..18usize).rev() {
-        xl = xl ^ keys.parray[i];
-        xr = f(xl, &keys) ^ xr;
+        xl ^= keys.parray[i];
+        xr ^= f(xl, keys);
         mem::swap(&mut xl, &mut xr);
     }
     mem::swap(&mut xl, &mut xr);
     xr ^= keys.parray[1];
     xl ^= keys.parray[0];
-    return (xl, xr);
+    (xl, xr)
+}
+
+/// Encodes `N` independent blocks at once. Rounds are interleaved across
+/// the blocks, so the CPU can overlap their S-box lookups.
+pub fn encode_blocks<const N: usize>(xl: [u32; N], xr: [u32; N], keys: &Keys) -> ([u32; N], [u32; N]) {
+    let mut xl = xl;
+    let mut xr = xr;
+    for i in 0usize..16usize {
+        for (l, r) in xl.iter_mut().zip(xr.iter_mut()) {
+            *l ^= keys.parray[i];
+            *r ^= f(*l, keys);
+        }
+        mem::swap(&mut xl, &mut xr);
+    }
+    mem::swap(&mut xl, &mut xr);
+    for (l, r) in xl.iter_mut().zip(xr.iter_mut()) {
+        *r ^= keys.parray[16];
+        *l ^= keys.parray[17];
+    }
+    (xl, xr)
+}
+
+/// Decoding counterpart of `encode_blocks`.
+pub fn decode_blocks<const N: usize>(xl: [u32; N], xr: [u32; N], keys: &Keys) -> ([u32; N], [u32; N]) {
+    let mut xl = xl;
+    let mut xr = xr;
+    for i in (2..18usize).rev() {
+        for (l, r) in xl.iter_mut().zip(xr.iter_mut()) {
+            *l ^= keys.parray[i];
+            *r ^= f(*l, keys);
+        }
+        mem::swap(&mut xl, &mut xr);
+    }
+    mem::swap(&mut xl, &mut xr);
+    for (l, r) in xl.iter_mut().zip(xr.iter_mut()) {
+        *r ^= keys.parray[1];
+        *l ^= keys.parray[0];
+    }
+    (xl, xr)
 }
 
 pub fn bytes_to_u32<I>(arr: I) -> u32
@@ -75,7 +135,7 @@ impl Keys {
             }
         }
     
-        return keys;
+        keys
     }
 }
 
@@ -192,6 +252,32 @@ mod tests {
         }
     }
 
+    #[test]
+    fn multi_block_test() {
+        let keys = Keys::new(&set_key);
+        let (enc_l, enc_r) = encode_blocks(plain_l, plain_r, &keys);
+        for i in 0..VAR_KEY_TESTS {
+            assert_eq!((enc_l[i], enc_r[i]), encode_block(plain_l[i], plain_r[i], &keys));
+        }
+        assert_eq!((plain_l, plain_r), decode_blocks(enc_l, enc_r, &keys));
+    }
+
+    #[test]
+    fn f_ct_test() {
+        for key in [&var_key[4][..], &set_key[..]] {
+            let keys = Keys::new(key);
+            let mut x = 0x01234567u32;
+            for _ in 0..10000 {
+                assert_eq!(f_fast(x, &keys), f_ct(x, &keys));
+                x = x.wrapping_mul(1664525).wrapping_add(1013904223);
+            }
+            for b in 0..=255u8 {
+                let x = u32::from_be_bytes([b; 4]);
+                assert_eq!(f_fast(x, &keys), f_ct(x, &keys));
+            }
+        }
+    }
+
     #[test]
     fn bytes_to_u32_test() {
         let mut cycle = [1, 2, 3u8].as_slice().iter().cycle();
@@ -0,0 +1,59 @@
+//! Checks on the input and output files.
+
+use std::io::{ErrorKind, Result};
+use std::path::Path;
+
+/// Whether `in_path` and `out_path` are the same file, also through hard links or
+/// bind mounts. Creating the output would then truncate the input before it is read.
+/// A missing output is never the input.
+#[cfg(unix)]
+pub fn same_file(in_path: &Path, out_path: &Path) -> Result<bool> {
+    use std::os::unix::fs::MetadataExt;
+
+    let output = match out_path.metadata() {
+        Ok(output) => output,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(false),
+        Err(e) => return Err(e),
+    };
+    let input = in_path.metadata()?;
+    Ok((input.dev(), input.ino()) == (output.dev(), output.ino()))
+}
+
+/// Fallback comparing canonical paths, which misses hard links
+#[cfg(not(unix))]
+pub fn same_file(in_path: &Path, out_path: &Path) -> Result<bool> {
+    match out_path.canonicalize() {
+        Ok(output) => Ok(output == in_path.canonicalize()?),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::fs;
+
+    #[test]
+    fn same_file_test() {
+        let dir = env::temp_dir().join(format!("blowfish-files-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let (a, b) = (dir.join("a"), dir.join("b"));
+        fs::write(&a, b"a").unwrap();
+
+        assert!(same_file(&a, &a).unwrap());
+        assert!(same_file(&a, &dir.join(".").join("a")).unwrap());
+        assert!(!same_file(&a, &b).unwrap());
+        fs::write(&b, b"a").unwrap();
+        assert!(!same_file(&a, &b).unwrap());
+        #[cfg(unix)]
+        {
+            fs::remove_file(&b).unwrap();
+            fs::hard_link(&a, &b).unwrap();
+            assert!(same_file(&a, &b).unwrap());
+        }
+        assert!(same_file(&dir.join("missing"), &a).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,6 @@
+pub mod blowfish;
+pub mod consts;
+pub mod files;
+pub mod modes;
+#[cfg(feature = "mmap")]
+pub mod mapped;
@@ -1,84 +1,263 @@
-use std::io::{BufReader, BufWriter, Write};
+use std::io::{self, BufReader, BufWriter, Read, Write};
 use std::fs::File;
 use std::env;
-use std::collections::HashMap;
-
-pub mod blowfish;
-pub mod consts;
-pub mod modes;
-
-fn main() {
-    let mut modes_map: HashMap<&str, [fn(&mut BufReader<File>, &[u8], &[u8], &mut BufWriter<File>) -> std::io::Result<()>; 2]> = HashMap::new();
-    modes_map.insert("ecb", [|r, p, _, w| modes::enc_ecb(r, p, w), |r, p, _, w| modes::dec_ecb(r, p, w)]);
-    modes_map.insert("cbc", [modes::enc_cbc, modes::dec_cbc]);
-    modes_map.insert("cfb", [modes::enc_cfb, modes::dec_cfb]);
-    let valid_modes: Vec<&str> = modes_map.keys().map(|s| *s).collect();
-
-    let args: Vec<String> = env::args().collect();
-    if args.len() < 4 || ["-h", "--help"].contains(&args[1].as_str()) {
-        println!("HELP");
-        return;
+use std::process::ExitCode;
+use std::thread;
+
+use blowfish::consts;
+use blowfish::files::same_file;
+use blowfish::modes::{self, Mode};
+
+mod terminal;
+
+const USAGE: &str = "\
+Usage: blowfish (-e | -d) [OPTIONS] [INPUT] [OUTPUT]
+
+Encrypts or decrypts INPUT into OUTPUT with Blowfish.
+A missing path or \"-\" means stdin or stdout.
+
+Options:
+  -e, --encrypt            Encrypt the input
+  -d, --decrypt            Decrypt the input
+  -m, --mode MODE          Mode of operation: ecb, cbc, cfb, ctr [default: cbc]
+  -k, --key-hex HEX        Key as 1-72 bytes in hex
+      --key-file PATH      Read the key in hex from a file, a final newline is ignored
+      --key-file-raw PATH  Read raw key bytes from a file
+      --key-fd FD          Read the key in hex from an open file descriptor
+      --key-fd-raw FD      Read raw key bytes from an open file descriptor
+      --key-env VAR        Take the key in hex from an environment variable
+      --iv HEX             Initialization vector, 8 bytes in hex (all modes except ecb)
+      --padding PADDING    Padding for ecb and cbc: pkcs7, none [default: pkcs7]
+  -t, --threads N          Threads for parallelizable modes [default: number of CPUs]
+      --mmap               Memory-map input and output files
+  -h, --help               Print this message
+
+Without a key option, the key is asked for on the terminal without echo (unix only).";
+
+const MAX_KEY_LEN: usize = 72;
+
+/// How a key file or file descriptor holds the key
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum KeyFormat {
+    /// Hex digits, a final newline is ignored
+    Hex,
+    /// Key bytes as they are
+    Raw,
+}
+
+#[derive(Debug, PartialEq)]
+enum KeySource {
+    Hex(String),
+    File(String, KeyFormat),
+    Fd(u32, KeyFormat),
+    Env(String),
+    Prompt,
+}
+
+#[derive(Debug, PartialEq)]
+struct Options {
+    encrypt: bool,
+    mode: Mode,
+    key: KeySource,
+    iv: Vec<u8>,
+    pkcs7: bool,
+    threads: usize,
+    mmap: bool,
+    input: String,
+    output: String,
+}
+
+/// Result of parsing the command line
+#[derive(Debug, PartialEq)]
+enum Command {
+    Help,
+    Run(Options),
+}
+
+fn parse_args<I: IntoIterator<Item = String>>(args: I) -> Result<Command, String> {
+    let mut args = args.into_iter();
+    let mut encrypt = None;
+    let mut mode = Mode::Cbc;
+    let mut key = KeySource::Prompt;
+    let mut iv = None;
+    let mut pkcs7 = true;
+    let mut threads = thread::available_parallelism().map_or(1, |n| n.get());
+    let mut mmap = false;
+    let mut paths = Vec::new();
+
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or_else(|| format!("missing value for {}", arg));
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(Command::Help),
+            "-e" | "--encrypt" => encrypt = Some(true),
+            "-d" | "--decrypt" => encrypt = Some(false),
+            "-m" | "--mode" => mode = value()?.parse()?,
+            "-k" | "--key-hex" => key = KeySource::Hex(value()?),
+            "--key-file" => key = KeySource::File(value()?, KeyFormat::Hex),
+            "--key-file-raw" => key = KeySource::File(value()?, KeyFormat::Raw),
+            "--key-fd" => key = KeySource::Fd(parse_fd(&value()?)?, KeyFormat::Hex),
+            "--key-fd-raw" => key = KeySource::Fd(parse_fd(&value()?)?, KeyFormat::Raw),
+            "--key-env" => key = KeySource::Env(value()?),
+            "--iv" => iv = Some(parse_hex(&value()?, "iv")?),
+            "--padding" => pkcs7 = match value()?.as_str() {
+                "pkcs7" => true,
+                "none" => false,
+                other => return Err(format!("unknown padding '{}'", other)),
+            },
+            "-t" | "--threads" => threads = value()?.parse()
+                .ok().filter(|&n| n > 0)
+                .ok_or("threads should be a positive number")?,
+            "--mmap" => mmap = true,
+            "-" => paths.push(arg),
+            _ if arg.starts_with('-') => return Err(format!("unknown option '{}'", arg)),
+            _ => paths.push(arg),
+        }
     }
 
-    let in_path = &args[2];
-    let out_path = &args[3];
+    let encrypt = encrypt.ok_or("either --encrypt or --decrypt is required")?;
+    if paths.len() > 2 {
+        return Err("too many paths".to_owned());
+    }
+    let mut paths = paths.into_iter();
+    let input = paths.next().unwrap_or_else(|| "-".to_owned());
+    let output = paths.next().unwrap_or_else(|| "-".to_owned());
 
-    let encrypt = match args[1].as_str() {
-        "-e" | "--encrypt" => 0,
-        "-d" | "--decrypt" => 1,
-        _ => panic!("Incorrect option"),
+    let iv = match (mode.needs_iv(), iv) {
+        (true, Some(iv)) if iv.len() == consts::BLOCK_SIZE => iv,
+        (true, Some(_)) => return Err(format!("iv should be {} bytes", consts::BLOCK_SIZE)),
+        (true, None) => return Err(format!("--iv is required for {}", mode)),
+        (false, _) => Vec::new(),
     };
+    if mmap && (input == "-" || output == "-") {
+        return Err("--mmap needs file paths".to_owned());
+    }
+
+    Ok(Command::Run(Options { encrypt, mode, key, iv, pkcs7, threads, mmap, input, output }))
+}
+
+fn parse_hex(hexstr: &str, what: &str) -> Result<Vec<u8>, String> {
+    if !hexstr.len().is_multiple_of(2) || !hexstr.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(format!("{} should be an even number of hex digits", what));
+    }
+    Ok(hex_to_bytes(hexstr))
+}
+
+fn parse_fd(fd: &str) -> Result<u32, String> {
+    fd.parse().map_err(|_| "file descriptor should be a number".to_owned())
+}
 
-    let mut in_file = BufReader::new(File::open(in_path).expect("Wrong input path."));
-    let mut out_file = BufWriter::new(File::create(out_path).expect("Wrong output path."));
-    
-    let mut mode = String::new();
-    let mut hex_password = String::new();
-    let mut hex_iv = String::new();
-    let password: Vec<u8>;
-    let mut iv = Vec::new();
-
-    while !valid_modes.contains(&mode.as_str()) {
-        mode.clear();
-        print!("Enter mode({}): ", valid_modes.join(","));
-        std::io::stdout().flush().unwrap();
-        std::io::stdin().read_line(&mut mode).unwrap();
-        mode = mode.trim().to_ascii_lowercase();
+/// Key from the contents of a key file. A final `\n` or `\r\n`, as left by editors
+/// and `echo`, is dropped from hex keys only, raw keys may end with any byte.
+fn decode_key(contents: Vec<u8>, format: KeyFormat) -> Result<Vec<u8>, String> {
+    match format {
+        KeyFormat::Raw => Ok(contents),
+        KeyFormat::Hex => {
+            let text = String::from_utf8(contents).map_err(|_| "key should be hex digits")?;
+            let text = text.strip_suffix('\n').map_or(text.as_str(), |t| t.strip_suffix('\r').unwrap_or(t));
+            parse_hex(text, "key")
+        }
     }
+}
 
-    if !mode.eq_ignore_ascii_case("ecb") {
-        loop {
-            hex_iv.clear();
-            print!("Enter iv (8 bytes, hex): ");
-            std::io::stdout().flush().unwrap();
-            std::io::stdin().read_line(&mut hex_iv).unwrap();
-            hex_iv = hex_iv.trim().to_owned();
-
-            if hex_iv.len() != consts::BLOCK_SIZE * 2 ||
-               !hex_iv.chars().all(|c| c.is_ascii_hexdigit()) {
-                println!("Incorrect iv.");
-            } else { break; }
+/// Gets the key from `source`, looking environment variables up with `var`.
+fn read_key(
+    source: &KeySource,
+    encrypt: bool,
+    var: impl Fn(&str) -> Result<String, env::VarError>,
+) -> Result<Vec<u8>, String> {
+    let key = match source {
+        KeySource::Hex(hex) => parse_hex(hex, "key")?,
+        KeySource::File(path, format) => decode_key(std::fs::read(path)
+            .map_err(|e| format!("can't read key file {}: {}", path, e))?, *format)?,
+        KeySource::Fd(fd, format) => decode_key(read_fd(*fd)
+            .map_err(|e| format!("can't read key from file descriptor {}: {}", fd, e))?, *format)?,
+        KeySource::Env(name) => parse_hex(&var(name)
+            .map_err(|e| format!("can't read key from ${}: {}", name, e))?, "key")?,
+        KeySource::Prompt => {
+            let prompt = format!("Enter key (1-{} bytes, hex): ", MAX_KEY_LEN);
+            let hex_key = terminal::read_secret(&prompt)
+                .map_err(|e| format!("no key given and can't ask for it: {}", e))?;
+            if encrypt && terminal::read_secret("Repeat key: ").map_err(|e| e.to_string())? != hex_key {
+                return Err("keys don't match".to_owned());
+            }
+            parse_hex(hex_key.trim(), "key")?
         }
-        iv = hex_to_bytes(&hex_iv);
+    };
+    if key.is_empty() || key.len() > MAX_KEY_LEN {
+        return Err(format!("key should be 1-{} bytes", MAX_KEY_LEN));
+    }
+    Ok(key)
+}
+
+#[cfg(unix)]
+fn read_fd(fd: u32) -> io::Result<Vec<u8>> {
+    std::fs::read(format!("/dev/fd/{}", fd))
+}
+
+#[cfg(not(unix))]
+fn read_fd(_: u32) -> io::Result<Vec<u8>> {
+    Err(io::Error::other("not supported on this platform"))
+}
+
+fn run(opts: &Options) -> Result<(), String> {
+    if opts.input != "-" && opts.output != "-" && same_file(opts.input.as_ref(), opts.output.as_ref())
+        .map_err(|e| format!("can't open {}: {}", opts.input, e))? {
+        return Err("input and output are the same file".to_owned());
+    }
+    let key = read_key(&opts.key, opts.encrypt, |name| env::var(name))?;
+
+    if opts.mmap {
+        return process_mapped(opts, &key);
     }
 
-    loop {
-        hex_password.clear();
-        print!("Enter password (1-72 bytes, hex): ");
-        std::io::stdout().flush().unwrap();
-        std::io::stdin().read_line(&mut hex_password).unwrap();
-        hex_password = hex_password.trim().to_owned();
-
-        if hex_password.len() < 1 || hex_password.len() > 72 ||
-           !hex_password.chars().all(|c| c.is_ascii_hexdigit()) {
-            println!("Incorrect password");
-        } else { break; }
+    let mut input: Box<dyn Read> = if opts.input == "-" {
+        Box::new(io::stdin().lock())
+    } else {
+        Box::new(BufReader::new(File::open(&opts.input)
+            .map_err(|e| format!("can't open {}: {}", opts.input, e))?))
+    };
+    let mut output: Box<dyn Write> = if opts.output == "-" {
+        Box::new(io::stdout().lock())
+    } else {
+        Box::new(BufWriter::new(File::create(&opts.output)
+            .map_err(|e| format!("can't create {}: {}", opts.output, e))?))
+    };
+
+    let process = if opts.encrypt { modes::encrypt } else { modes::decrypt };
+    process(&mut input, &mut output, opts.mode, &key, &opts.iv, opts.pkcs7, opts.threads)
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(feature = "mmap")]
+fn process_mapped(opts: &Options, key: &[u8]) -> Result<(), String> {
+    blowfish::mapped::process_file(opts.input.as_ref(), opts.output.as_ref(), opts.mode,
+        opts.encrypt, key, &opts.iv, opts.pkcs7, opts.threads)
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(not(feature = "mmap"))]
+fn process_mapped(_: &Options, _: &[u8]) -> Result<(), String> {
+    Err("built without mmap support".to_owned())
+}
+
+fn main() -> ExitCode {
+    match parse_args(env::args().skip(1)) {
+        Ok(Command::Help) => {
+            println!("{}", USAGE);
+            ExitCode::SUCCESS
+        }
+        Ok(Command::Run(opts)) => match run(&opts) {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(e) => {
+                eprintln!("blowfish: {}", e);
+                ExitCode::FAILURE
+            }
+        },
+        Err(e) => {
+            eprintln!("blowfish: {}\n\n{}", e, USAGE);
+            ExitCode::from(2)
+        }
     }
-    password = hex_to_bytes(&hex_password);
-    
-    let algorithm = modes_map.get(mode.as_str()).unwrap()[encrypt];
-    algorithm(&mut in_file, &password, &iv, &mut out_file).unwrap();
-    println!("Done.");
 }
 
 fn hex_to_bytes(hexstr: &str) -> Vec<u8> {
@@ -92,9 +271,128 @@ fn hex_to_bytes(hexstr: &str) -> Vec<u8> {
 mod tests {
     use super::*;
 
+    fn args(line: &str) -> Vec<String> {
+        line.split_whitespace().map(String::from).collect()
+    }
+
     #[test]
     fn hex_to_bytes_test() {
         assert_eq!(hex_to_bytes("Af901C").as_slice(), [0xaf, 0x90, 0x1c]);
         assert_eq!(hex_to_bytes("Af901Ce").as_slice(), [0xaf, 0x90, 0x1c]);
     }
+
+    #[test]
+    fn parse_args_test() {
+        let parsed = parse_args(args("-d --mode ctr --iv fedcba9876543210 -k 0123 -t 3 --padding none in.bin -")).unwrap();
+        assert_eq!(parsed, Command::Run(Options {
+            encrypt: false,
+            mode: Mode::Ctr,
+            key: KeySource::Hex("0123".to_owned()),
+            iv: vec![0xfe, 0xdc, 0xba, 0x98, 0x76, 0x54, 0x32, 0x10],
+            pkcs7: false,
+            threads: 3,
+            mmap: false,
+            input: "in.bin".to_owned(),
+            output: "-".to_owned(),
+        }));
+
+        match parse_args(args("-e -m ecb --key-fd 3")).unwrap() {
+            Command::Run(opts) => assert_eq!(opts.key, KeySource::Fd(3, KeyFormat::Hex)),
+            Command::Help => panic!(),
+        }
+
+        match parse_args(args("-e -m ecb --key-file key")).unwrap() {
+            Command::Run(opts) => {
+                assert_eq!(opts.key, KeySource::File("key".to_owned(), KeyFormat::Hex));
+                assert_eq!((opts.input.as_str(), opts.output.as_str()), ("-", "-"));
+                assert!(opts.iv.is_empty());
+            }
+            Command::Help => panic!(),
+        }
+
+        match parse_args(args("-e -m ecb --key-fd-raw 4")).unwrap() {
+            Command::Run(opts) => assert_eq!(opts.key, KeySource::Fd(4, KeyFormat::Raw)),
+            Command::Help => panic!(),
+        }
+
+        assert_eq!(parse_args(args("-e --help")), Ok(Command::Help));
+    }
+
+    #[test]
+    fn same_file_test() {
+        let dir = env::temp_dir().join(format!("blowfish-cli-same-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("data");
+        std::fs::write(&path, b"contents").unwrap();
+        let mut outputs = vec![dir.join(".").join("data")];
+        #[cfg(unix)]
+        {
+            std::fs::hard_link(&path, dir.join("link")).unwrap();
+            outputs.push(dir.join("link"));
+        }
+        for output in outputs {
+            let line = format!("-e -m ctr --iv 0011223344556677 -k 0123 {} {}", path.display(), output.display());
+            let Command::Run(opts) = parse_args(args(&line)).unwrap() else { panic!() };
+            assert_eq!(run(&opts), Err("input and output are the same file".to_owned()));
+        }
+        assert_eq!(std::fs::read(&path).unwrap(), b"contents");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn read_key_test() {
+        let var = |name: &str| match name {
+            "BLOWFISH_TEST_KEY" => Ok("0123abcd".to_owned()),
+            _ => Err(env::VarError::NotPresent),
+        };
+        assert_eq!(read_key(&KeySource::Hex("00ff".to_owned()), true, var), Ok(vec![0x00, 0xff]));
+        assert!(read_key(&KeySource::Hex("".to_owned()), true, var).is_err());
+        assert!(read_key(&KeySource::Hex("00".repeat(MAX_KEY_LEN + 1)), true, var).is_err());
+
+        assert_eq!(read_key(&KeySource::Env("BLOWFISH_TEST_KEY".to_owned()), false, var), Ok(vec![0x01, 0x23, 0xab, 0xcd]));
+        assert!(read_key(&KeySource::Env("BLOWFISH_TEST_MISSING_KEY".to_owned()), false, var).is_err());
+
+        let path = env::temp_dir().join(format!("blowfish-key-{}", std::process::id()));
+        let file = |format| KeySource::File(path.to_str().unwrap().to_owned(), format);
+        for (contents, hex, raw) in [
+            (&b"0123ab\n"[..], Some(&[0x01, 0x23, 0xab][..]), &b"0123ab\n"[..]),
+            (b"0123ab\r\n", Some(&[0x01, 0x23, 0xab]), b"0123ab\r\n"),
+            (b"0123ab", Some(&[0x01, 0x23, 0xab]), b"0123ab"),
+            (b"0123ab\n\n", None, b"0123ab\n\n"),
+            (b"0123ab\r", None, b"0123ab\r"),
+            // Binary key ending with a newline byte stays as it is
+            (b"\xff\x00\x0a", None, b"\xff\x00\x0a"),
+            (b"\n", None, b"\n"),
+        ] {
+            std::fs::write(&path, contents).unwrap();
+            assert_eq!(read_key(&file(KeyFormat::Hex), false, var).ok().as_deref(), hex, "{:?}", contents);
+            assert_eq!(read_key(&file(KeyFormat::Raw), false, var).as_deref(), Ok(raw));
+            #[cfg(unix)]
+            {
+                use std::os::fd::AsRawFd;
+                let file = File::open(&path).unwrap();
+                let fd = file.as_raw_fd() as u32;
+                assert_eq!(read_key(&KeySource::Fd(fd, KeyFormat::Raw), false, var).as_deref(), Ok(raw));
+            }
+        }
+        std::fs::write(&path, b"").unwrap();
+        assert!(read_key(&file(KeyFormat::Raw), false, var).is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn parse_args_errors_test() {
+        assert!(parse_args(args("in out")).is_err());
+        assert!(parse_args(args("-e -m cbc in out")).is_err());
+        assert!(parse_args(args("-e -m ofb in out")).is_err());
+        assert!(parse_args(args("-e --iv 0011 in out")).is_err());
+        assert!(parse_args(args("-e --iv xx11223344556677 in out")).is_err());
+        assert!(parse_args(args("-e -m ecb --padding zero")).is_err());
+        assert!(parse_args(args("-e -m ecb -t 0")).is_err());
+        assert!(parse_args(args("-e -m ecb --mmap in")).is_err());
+        assert!(parse_args(args("-e -m ecb a b c")).is_err());
+        assert!(parse_args(args("-e -m ecb --bogus")).is_err());
+        assert!(parse_args(args("-e -m")).is_err());
+        assert!(parse_args(args("-e -m ecb --key-fd x")).is_err());
+    }
 }
@@ -0,0 +1,109 @@
+//! Whole-file encryption over memory-mapped input and output.
+
+use std::fs::{File, OpenOptions};
+use std::io::{Error, ErrorKind, Result};
+use std::path::Path;
+
+use memmap2::{Mmap, MmapMut};
+
+use crate::blowfish::Keys;
+use crate::consts::BLOCK_SIZE;
+use crate::files::same_file;
+use crate::modes::*;
+
+/// Length of the output for `len` bytes of input
+fn output_len(mode: Mode, encrypt: bool, pkcs7: bool, len: usize) -> usize {
+    match (mode.is_block() && pkcs7, encrypt) {
+        (true, true) => len + BLOCK_SIZE - len % BLOCK_SIZE,
+        (true, false) => len - len % BLOCK_SIZE,
+        _ => len,
+    }
+}
+
+/// Encrypts or decrypts the file at `in_path` into `out_path`,
+/// with the same parameters as `modes::encrypt`.
+#[allow(clippy::too_many_arguments)]
+pub fn process_file(in_path: &Path, out_path: &Path, mode: Mode, encrypt: bool, key: &[u8], iv: &[u8], pkcs7: bool, threads: usize) -> Result<()> {
+    let in_file = File::open(in_path)?;
+    if same_file(in_path, out_path)? {
+        return Err(Error::new(ErrorKind::InvalidInput, "input and output are the same file"));
+    }
+    let in_len = usize::try_from(in_file.metadata()?.len())
+        .map_err(|_| Error::new(ErrorKind::InvalidInput, "input is too large to map"))?;
+    if mode.is_block() && !pkcs7 && !in_len.is_multiple_of(BLOCK_SIZE) {
+        return Err(Error::new(ErrorKind::InvalidData, "input is not a multiple of the block size"));
+    }
+    let out_len = output_len(mode, encrypt, pkcs7, in_len);
+
+    let out_file = OpenOptions::new().read(true).write(true).create(true).truncate(true).open(out_path)?;
+    out_file.set_len(out_len as u64)?;
+    if out_len == 0 { return Ok(()); }
+
+    // SAFETY: the maps are only valid while no one else modifies the files,
+    // same as for any other tool working on them.
+    let mut out = unsafe { MmapMut::map_mut(&out_file)? };
+    if in_len > 0 {
+        let input = unsafe { Mmap::map(&in_file)? };
+        out[..in_len.min(out_len)].copy_from_slice(&input[..in_len.min(out_len)]);
+    }
+    if out_len > in_len {
+        let padded = pad_pkcs7(&out[in_len / BLOCK_SIZE * BLOCK_SIZE..in_len], BLOCK_SIZE);
+        out[in_len / BLOCK_SIZE * BLOCK_SIZE..].copy_from_slice(&padded);
+    }
+
+    let keys = Keys::new(key);
+    let mut iv = if mode.needs_iv() { iv[..BLOCK_SIZE].try_into().unwrap() } else { [0; BLOCK_SIZE] };
+    crypt_in_place(&mut out, mode, encrypt, &keys, &mut iv, threads);
+    out.flush()?;
+
+    if !encrypt && mode.is_block() && pkcs7 {
+        let tail = unpad_pkcs7(&out[out_len-BLOCK_SIZE..], BLOCK_SIZE).len();
+        drop(out);
+        out_file.set_len((out_len - BLOCK_SIZE + tail) as u64)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::fs;
+
+    const KEY: [u8; 8] = [0x01, 0x23, 0x45, 0x67, 0x89, 0xAB, 0xCD, 0xEF];
+    const IV: [u8; 8] = [0xFE, 0xDC, 0xBA, 0x98, 0x76, 0x54, 0x32, 0x10];
+
+    #[test]
+    fn process_file_test() {
+        let dir = env::temp_dir().join(format!("blowfish-mapped-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let plain_path = dir.join("plain");
+        let enc_path = dir.join("enc");
+        let dec_path = dir.join("dec");
+
+        for len in [0, 7, 8, 100_003] {
+            let plain: Vec<u8> = (0..len).map(|i| (i * 7 + 3) as u8).collect();
+            fs::write(&plain_path, &plain).unwrap();
+            for mode in Mode::ALL {
+                process_file(&plain_path, &enc_path, mode, true, &KEY, &IV, true, 3).unwrap();
+                let mut expected = Vec::new();
+                encrypt(&mut plain.as_slice(), &mut expected, mode, &KEY, &IV, true, 1).unwrap();
+                assert_eq!(expected, fs::read(&enc_path).unwrap(), "{} {}", mode, len);
+
+                process_file(&enc_path, &dec_path, mode, false, &KEY, &IV, true, 3).unwrap();
+                assert_eq!(plain, fs::read(&dec_path).unwrap(), "{} {}", mode, len);
+            }
+        }
+
+        let same = process_file(&plain_path, &dir.join(".").join("plain"), Mode::Ctr, true, &KEY, &IV, true, 1);
+        assert_eq!(same.unwrap_err().kind(), ErrorKind::InvalidInput);
+        #[cfg(unix)]
+        {
+            fs::hard_link(&plain_path, dir.join("link")).unwrap();
+            let same = process_file(&plain_path, &dir.join("link"), Mode::Ctr, true, &KEY, &IV, true, 1);
+            assert_eq!(same.unwrap_err().kind(), ErrorKind::InvalidInput);
+        }
+        assert_eq!(fs::read(&plain_path).unwrap().len(), 100_003);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,28 +1,67 @@
-use std::io::{Read, Write, Result};
+use std::fmt;
+use std::io::{Error, ErrorKind, Read, Write, Result};
+use std::str::FromStr;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread::{self, Scope};
 
 use crate::blowfish::*;
 use crate::consts::{BLOCK_SIZE};
 
-fn enc8(bytes: &[u8], keys: &Keys) -> Vec<u8> {
-    let (p1, p2) = encode_block(bytes_to_u32(&bytes[..4]), bytes_to_u32(&bytes[4..]), &keys);
-    let mut v = Vec::with_capacity(BLOCK_SIZE);
-    v.extend_from_slice(&p1.to_be_bytes());
-    v.extend_from_slice(&p2.to_be_bytes());
-    return v
+/// Number of blocks encoded together on the interleaved path
+const LANES: usize = 4;
+/// Blocks read from the input at once, per thread
+const CHUNK_BLOCKS: usize = 8192;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Mode {
+    Ecb,
+    Cbc,
+    Cfb,
+    Ctr,
+}
+
+impl Mode {
+    pub const ALL: [Mode; 4] = [Mode::Ecb, Mode::Cbc, Mode::Cfb, Mode::Ctr];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Mode::Ecb => "ecb",
+            Mode::Cbc => "cbc",
+            Mode::Cfb => "cfb",
+            Mode::Ctr => "ctr",
+        }
+    }
+
+    /// Whether the mode works on whole blocks and needs padding
+    pub fn is_block(self) -> bool {
+        matches!(self, Mode::Ecb | Mode::Cbc)
+    }
+
+    pub fn needs_iv(self) -> bool {
+        self != Mode::Ecb
+    }
 }
 
-fn dec8(bytes: &[u8], keys: &Keys) -> Vec<u8> {
-    let (p1, p2) = decode_block(bytes_to_u32(&bytes[..4]), bytes_to_u32(&bytes[4..]), &keys);
-    let mut v = Vec::with_capacity(BLOCK_SIZE);
-    v.extend_from_slice(&p1.to_be_bytes());
-    v.extend_from_slice(&p2.to_be_bytes());
-    return v
+impl fmt::Display for Mode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Mode {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        Mode::ALL.into_iter()
+            .find(|m| m.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| format!("unknown mode '{}'", s))
+    }
 }
 
 pub fn pad_pkcs7(message: &[u8], block_size: usize) -> Vec<u8> {
     let padding = (block_size - (message.len() % block_size)) as u8;
     let mut v = message.to_vec();
-    v.resize(block_size, padding);
+    v.resize(message.len() + padding as usize, padding);
     v
 }
 
@@ -34,76 +73,449 @@ pub fn unpad_pkcs7(block: &[u8], block_size: usize) -> Vec<u8> {
     } else { block.to_vec() }
 }
 
-pub fn enc_ecb<R, W>(input: &mut R, key: &[u8], out: &mut W) -> Result<()>
-where 
-    R: Read,
-    W: Write,
+fn load_lanes(group: &[u8]) -> ([u32; LANES], [u32; LANES]) {
+    let mut xl = [0u32; LANES];
+    let mut xr = [0u32; LANES];
+    for (j, block) in group.chunks_exact(BLOCK_SIZE).enumerate() {
+        xl[j] = bytes_to_u32(&block[..4]);
+        xr[j] = bytes_to_u32(&block[4..]);
+    }
+    (xl, xr)
+}
+
+fn store_block(block: &mut [u8], l: u32, r: u32) {
+    block[..4].copy_from_slice(&l.to_be_bytes());
+    block[4..].copy_from_slice(&r.to_be_bytes());
+}
+
+fn store_lanes(group: &mut [u8], xl: [u32; LANES], xr: [u32; LANES]) {
+    for (j, block) in group.chunks_exact_mut(BLOCK_SIZE).enumerate() {
+        store_block(block, xl[j], xr[j]);
+    }
+}
+
+/// Xors `block` (possibly shorter than `BLOCK_SIZE`) with the block `(l, r)`.
+fn xor_block(block: &mut [u8], l: u32, r: u32) {
+    let key = ((l as u64) << 32 | r as u64).to_be_bytes();
+    block.iter_mut().zip(key).for_each(|(a, b)| *a ^= b);
+}
+
+fn xor_lanes(group: &mut [u8], xl: [u32; LANES], xr: [u32; LANES]) {
+    for (j, block) in group.chunks_mut(BLOCK_SIZE).enumerate() {
+        xor_block(block, xl[j], xr[j]);
+    }
+}
+
+/// Length of a piece when splitting `len` bytes between `threads`,
+/// rounded up to whole blocks.
+fn piece_len(len: usize, threads: usize) -> usize {
+    len.div_ceil(BLOCK_SIZE).div_ceil(threads.max(1)).max(1) * BLOCK_SIZE
+}
+
+/// Splits `data` into up to `threads` runs of whole blocks and calls `f` on
+/// each one in its own thread, along with the offset of the run in `data`.
+fn for_each_piece<F>(data: &mut [u8], threads: usize, f: F)
+where
+    F: Fn(usize, &mut [u8]) + Sync,
 {
-    let keys = Keys::new(key);
-    let mut buf = vec![0u8; BLOCK_SIZE];
-    let mut read_len: usize;
+    let piece = piece_len(data.len(), threads);
+    if piece >= data.len() {
+        f(0, data);
+        return;
+    }
+    thread::scope(|s| {
+        for (i, chunk) in data.chunks_mut(piece).enumerate() {
+            let f = &f;
+            s.spawn(move || f(i * piece, chunk));
+        }
+    });
+}
+
+/// Ciphertext blocks preceding each piece of `data`, as split by `for_each_piece`.
+fn piece_ivs(data: &[u8], iv: &[u8; BLOCK_SIZE], threads: usize) -> Vec<[u8; BLOCK_SIZE]> {
+    let piece = piece_len(data.len(), threads);
+    let mut ivs = vec![*iv];
+    ivs.extend((piece..data.len()).step_by(piece)
+        .map(|start| -> [u8; BLOCK_SIZE] { data[start-BLOCK_SIZE..start].try_into().unwrap() }));
+    ivs
+}
 
-    loop {
-        read_len = input.read(&mut buf)?;
-        if read_len < BLOCK_SIZE {
-            buf = pad_pkcs7(&buf[..read_len], BLOCK_SIZE);
+fn ecb_piece(data: &mut [u8], keys: &Keys, decode: bool) {
+    for group in data.chunks_mut(BLOCK_SIZE * LANES) {
+        let (xl, xr) = load_lanes(group);
+        let (xl, xr) = if decode { decode_blocks(xl, xr, keys) } else { encode_blocks(xl, xr, keys) };
+        store_lanes(group, xl, xr);
+    }
+}
+
+/// Encrypts whole blocks of `data` in place.
+pub fn enc_ecb_in_place(data: &mut [u8], keys: &Keys, threads: usize) {
+    for_each_piece(data, threads, |_, piece| ecb_piece(piece, keys, false));
+}
+
+/// Decrypts whole blocks of `data` in place.
+pub fn dec_ecb_in_place(data: &mut [u8], keys: &Keys, threads: usize) {
+    for_each_piece(data, threads, |_, piece| ecb_piece(piece, keys, true));
+}
+
+fn ctr_piece(data: &mut [u8], keys: &Keys, counter: u64) {
+    let mut counter = counter;
+    for group in data.chunks_mut(BLOCK_SIZE * LANES) {
+        let mut xl = [0u32; LANES];
+        let mut xr = [0u32; LANES];
+        for j in 0..LANES {
+            let block = counter.wrapping_add(j as u64);
+            xl[j] = (block >> 32) as u32;
+            xr[j] = block as u32;
         }
-        let encoded = enc8(&buf, &keys);
-        out.write(&encoded)?;
-        if read_len < BLOCK_SIZE { break; }
+        let (xl, xr) = encode_blocks(xl, xr, keys);
+        xor_lanes(group, xl, xr);
+        counter = counter.wrapping_add(LANES as u64);
     }
-    out.flush()?;
-    Ok(())
 }
 
-pub fn dec_ecb<R, W>(input: &mut R, key: &[u8], out: &mut W) -> Result<()>
+/// Xors `data` with the CTR keystream starting from block number `counter`.
+/// Only the last block of a message may be partial.
+pub fn ctr_in_place(data: &mut [u8], keys: &Keys, counter: u64, threads: usize) {
+    for_each_piece(data, threads, |offset, piece| {
+        ctr_piece(piece, keys, counter.wrapping_add((offset / BLOCK_SIZE) as u64))
+    });
+}
+
+/// Encrypts whole blocks of `data` in place, updating `iv` to the last ciphertext block.
+pub fn enc_cbc_in_place(data: &mut [u8], keys: &Keys, iv: &mut [u8; BLOCK_SIZE]) {
+    for block in data.chunks_exact_mut(BLOCK_SIZE) {
+        block.iter_mut().zip(iv.iter()).for_each(|(a, b)| *a ^= b);
+        let (l, r) = encode_block(bytes_to_u32(&block[..4]), bytes_to_u32(&block[4..]), keys);
+        store_block(block, l, r);
+        iv.copy_from_slice(block);
+    }
+}
+
+fn dec_cbc_piece(data: &mut [u8], keys: &Keys, iv: [u8; BLOCK_SIZE]) {
+    let mut chain = [0u8; BLOCK_SIZE * (LANES + 1)];
+    chain[..BLOCK_SIZE].copy_from_slice(&iv);
+    for group in data.chunks_exact_mut(BLOCK_SIZE * LANES) {
+        chain[BLOCK_SIZE..].copy_from_slice(group);
+        let (xl, xr) = load_lanes(group);
+        let (xl, xr) = decode_blocks(xl, xr, keys);
+        store_lanes(group, xl, xr);
+        group.iter_mut().zip(chain.iter()).for_each(|(a, b)| *a ^= b);
+        chain.copy_within(BLOCK_SIZE * LANES.., 0);
+    }
+    let rest = data.chunks_exact_mut(BLOCK_SIZE * LANES).into_remainder();
+    for block in rest.chunks_exact_mut(BLOCK_SIZE) {
+        let prev: [u8; BLOCK_SIZE] = block.try_into().unwrap();
+        let (l, r) = decode_block(bytes_to_u32(&block[..4]), bytes_to_u32(&block[4..]), keys);
+        store_block(block, l, r);
+        block.iter_mut().zip(chain.iter()).for_each(|(a, b)| *a ^= b);
+        chain[..BLOCK_SIZE].copy_from_slice(&prev);
+    }
+}
+
+/// Decrypts whole blocks of `data` in place, updating `iv` to the last ciphertext block.
+pub fn dec_cbc_in_place(data: &mut [u8], keys: &Keys, iv: &mut [u8; BLOCK_SIZE], threads: usize) {
+    let full = data.len() / BLOCK_SIZE * BLOCK_SIZE;
+    let data = &mut data[..full];
+    if data.is_empty() { return; }
+    let ivs = piece_ivs(data, iv, threads);
+    let piece = piece_len(data.len(), threads);
+    iv.copy_from_slice(&data[data.len()-BLOCK_SIZE..]);
+    for_each_piece(data, threads, |offset, chunk| dec_cbc_piece(chunk, keys, ivs[offset / piece]));
+}
+
+/// Encrypts `data` in place, updating `iv` to the last ciphertext block.
+/// Only the last block of a message may be partial.
+pub fn enc_cfb_in_place(data: &mut [u8], keys: &Keys, iv: &mut [u8; BLOCK_SIZE]) {
+    for block in data.chunks_mut(BLOCK_SIZE) {
+        let (l, r) = encode_block(bytes_to_u32(&iv[..4]), bytes_to_u32(&iv[4..]), keys);
+        xor_block(block, l, r);
+        if block.len() == BLOCK_SIZE { iv.copy_from_slice(block); }
+    }
+}
+
+fn dec_cfb_piece(data: &mut [u8], keys: &Keys, iv: [u8; BLOCK_SIZE]) {
+    let mut chain = [0u8; BLOCK_SIZE * (LANES + 1)];
+    chain[..BLOCK_SIZE].copy_from_slice(&iv);
+    for group in data.chunks_mut(BLOCK_SIZE * LANES) {
+        chain[BLOCK_SIZE..BLOCK_SIZE + group.len()].copy_from_slice(group);
+        let (xl, xr) = load_lanes(&chain[..BLOCK_SIZE * LANES]);
+        let (xl, xr) = encode_blocks(xl, xr, keys);
+        xor_lanes(group, xl, xr);
+        chain.copy_within(BLOCK_SIZE * LANES.., 0);
+    }
+}
+
+/// Decrypts `data` in place, updating `iv` to the last ciphertext block.
+/// Only the last block of a message may be partial.
+pub fn dec_cfb_in_place(data: &mut [u8], keys: &Keys, iv: &mut [u8; BLOCK_SIZE], threads: usize) {
+    if data.is_empty() { return; }
+    let ivs = piece_ivs(data, iv, threads);
+    let piece = piece_len(data.len(), threads);
+    let full = data.len() / BLOCK_SIZE * BLOCK_SIZE;
+    if full > 0 { iv.copy_from_slice(&data[full-BLOCK_SIZE..full]); }
+    for_each_piece(data, threads, |offset, chunk| dec_cfb_piece(chunk, keys, ivs[offset / piece]));
+}
+
+fn read_full<R: Read>(input: &mut R, buf: &mut [u8]) -> Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match input.read(&mut buf[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(filled)
+}
+
+enum Padding {
+    /// Pad the last block with PKCS#7
+    Add,
+    /// Drop trailing partial block and remove PKCS#7 padding from the last block
+    Strip,
+    /// Process whole blocks only, fail on a trailing partial block
+    Exact,
+    /// Process the stream as is
+    Keep,
+}
+
+impl Padding {
+    fn new(mode: Mode, encrypt: bool, pkcs7: bool) -> Self {
+        match (mode.is_block(), encrypt, pkcs7) {
+            (false, _, _) => Padding::Keep,
+            (true, _, false) => Padding::Exact,
+            (true, true, true) => Padding::Add,
+            (true, false, true) => Padding::Strip,
+        }
+    }
+}
+
+/// Whether pieces of a message can be processed independently, given the IV each starts with
+fn is_parallel(mode: Mode, encrypt: bool) -> bool {
+    matches!(mode, Mode::Ecb | Mode::Ctr) || !encrypt
+}
+
+/// IV that `crypt_in_place` leaves after a piece of `data` in a parallelizable mode,
+/// known before the piece is processed
+fn iv_after(data: &[u8], mode: Mode, iv: &[u8; BLOCK_SIZE]) -> [u8; BLOCK_SIZE] {
+    let full = data.len() / BLOCK_SIZE * BLOCK_SIZE;
+    match mode {
+        Mode::Ecb => *iv,
+        Mode::Ctr => u64::from_be_bytes(*iv).wrapping_add(data.len().div_ceil(BLOCK_SIZE) as u64).to_be_bytes(),
+        _ if full == 0 => *iv,
+        _ => data[full-BLOCK_SIZE..full].try_into().unwrap(),
+    }
+}
+
+/// Part of a chunk handed to a worker, with the IV it starts with
+struct Piece {
+    data: Vec<u8>,
+    iv: [u8; BLOCK_SIZE],
+}
+
+/// Thread running one mode over the pieces it is sent, until `jobs` is dropped
+struct Worker {
+    jobs: Sender<Piece>,
+    done: Receiver<Piece>,
+}
+
+impl Worker {
+    fn spawn<'scope>(s: &'scope Scope<'scope, '_>, mode: Mode, encrypt: bool, keys: &'scope Keys) -> Self {
+        let (jobs, pieces) = mpsc::channel::<Piece>();
+        let (finished, done) = mpsc::channel();
+        s.spawn(move || {
+            for mut piece in pieces {
+                crypt_in_place(&mut piece.data, mode, encrypt, keys, &mut piece.iv, 1);
+                if finished.send(piece).is_err() { break; }
+            }
+        });
+        Worker { jobs, done }
+    }
+}
+
+/// Reads `input` into consecutive pieces of `CHUNK_BLOCKS` blocks, returns the number of bytes read
+fn read_pieces<R: Read>(input: &mut R, pieces: &mut [Vec<u8>]) -> Result<usize> {
+    let mut total = 0;
+    let mut ended = false;
+    for piece in pieces {
+        piece.resize(BLOCK_SIZE * CHUNK_BLOCKS, 0);
+        let len = if ended { 0 } else { read_full(input, piece)? };
+        piece.truncate(len);
+        ended = len < BLOCK_SIZE * CHUNK_BLOCKS;
+        total += len;
+    }
+    Ok(total)
+}
+
+/// Last piece holding data, the end of the message
+fn last_piece(pieces: &mut [Vec<u8>]) -> &mut Vec<u8> {
+    let last = pieces.iter().rposition(|piece| !piece.is_empty()).unwrap_or(0);
+    &mut pieces[last]
+}
+
+/// Runs `mode` over consecutive `pieces`, each on its own worker when there are any
+fn crypt_pieces(pieces: &mut [Vec<u8>], workers: &[Worker], mode: Mode, encrypt: bool, keys: &Keys, iv: &mut [u8; BLOCK_SIZE]) {
+    if workers.is_empty() {
+        pieces.iter_mut().for_each(|piece| crypt_in_place(piece, mode, encrypt, keys, iv, 1));
+        return;
+    }
+    for (piece, worker) in pieces.iter_mut().zip(workers) {
+        let start = *iv;
+        *iv = iv_after(piece, mode, iv);
+        worker.jobs.send(Piece { data: std::mem::take(piece), iv: start }).unwrap();
+    }
+    for (piece, worker) in pieces.iter_mut().zip(workers) {
+        *piece = worker.done.recv().unwrap().data;
+    }
+}
+
+/// Reads `input` in chunks of `CHUNK_BLOCKS` blocks per thread, runs `mode` over each
+/// and writes the result to `out`. Parallelizable modes start a worker per thread once
+/// for the whole stream, worker `i` gets the `i`-th contiguous piece of every chunk.
+#[allow(clippy::too_many_arguments)]
+fn process_stream<R, W>(input: &mut R, out: &mut W, mode: Mode, encrypt: bool, keys: &Keys, mut iv: [u8; BLOCK_SIZE], pkcs7: bool, threads: usize) -> Result<()>
 where
     R: Read,
     W: Write,
 {
-    let keys = Keys::new(key);
-    let mut buf = vec![0u8; BLOCK_SIZE];
-    let mut read_len = input.read(&mut buf)?;
-    let mut decoded: Vec<u8>;
+    let padding = Padding::new(mode, encrypt, pkcs7);
+    let threads = threads.max(1);
+    thread::scope(|s| {
+        let workers: Vec<Worker> = if threads > 1 && is_parallel(mode, encrypt) {
+            (0..threads).map(|_| Worker::spawn(s, mode, encrypt, keys)).collect()
+        } else {
+            Vec::new()
+        };
+        let mut chunk = vec![Vec::new(); threads];
+        let mut next = vec![Vec::new(); threads];
+        let mut len = read_pieces(input, &mut chunk)?;
 
-    while read_len == BLOCK_SIZE {
-        decoded = dec8(&buf, &keys);
-        read_len = input.read(&mut buf)?;
-        if read_len < BLOCK_SIZE {
-            decoded = unpad_pkcs7(&decoded, BLOCK_SIZE);
+        loop {
+            let mut next_len = 0;
+            let last = if len < BLOCK_SIZE * CHUNK_BLOCKS * threads { true } else {
+                next_len = read_pieces(input, &mut next)?;
+                next_len == 0
+            };
+
+            match padding {
+                Padding::Add if last => {
+                    let piece = last_piece(&mut chunk);
+                    *piece = pad_pkcs7(piece, BLOCK_SIZE);
+                }
+                Padding::Strip => {
+                    let piece = last_piece(&mut chunk);
+                    piece.truncate(piece.len() / BLOCK_SIZE * BLOCK_SIZE);
+                }
+                Padding::Exact if len % BLOCK_SIZE != 0 => {
+                    return Err(Error::new(ErrorKind::InvalidData, "input is not a multiple of the block size"));
+                }
+                _ => {}
+            }
+            crypt_pieces(&mut chunk, &workers, mode, encrypt, keys, &mut iv);
+            if matches!(padding, Padding::Strip) && last {
+                let piece = last_piece(&mut chunk);
+                if !piece.is_empty() {
+                    let tail = unpad_pkcs7(&piece[piece.len()-BLOCK_SIZE..], BLOCK_SIZE).len();
+                    piece.truncate(piece.len() - BLOCK_SIZE + tail);
+                }
+            }
+            for piece in &chunk {
+                out.write_all(piece)?;
+            }
+
+            if last { break; }
+            std::mem::swap(&mut chunk, &mut next);
+            len = next_len;
+        }
+        out.flush()
+    })
+}
+
+fn to_iv(mode: Mode, iv: &[u8]) -> [u8; BLOCK_SIZE] {
+    if mode.needs_iv() { iv[..BLOCK_SIZE].try_into().unwrap() } else { [0; BLOCK_SIZE] }
+}
+
+/// Runs `mode` over `data` in place. `iv` is updated, so that the next call
+/// continues the same message. Block modes only process whole blocks, and
+/// only the last block of a message may be partial for the others.
+pub fn crypt_in_place(data: &mut [u8], mode: Mode, encrypt: bool, keys: &Keys, iv: &mut [u8; BLOCK_SIZE], threads: usize) {
+    match (mode, encrypt) {
+        (Mode::Ecb, true) => enc_ecb_in_place(data, keys, threads),
+        (Mode::Ecb, false) => dec_ecb_in_place(data, keys, threads),
+        (Mode::Cbc, true) => enc_cbc_in_place(data, keys, iv),
+        (Mode::Cbc, false) => dec_cbc_in_place(data, keys, iv, threads),
+        (Mode::Cfb, true) => enc_cfb_in_place(data, keys, iv),
+        (Mode::Cfb, false) => dec_cfb_in_place(data, keys, iv, threads),
+        (Mode::Ctr, _) => {
+            let counter = u64::from_be_bytes(*iv);
+            ctr_in_place(data, keys, counter, threads);
+            *iv = counter.wrapping_add(data.len().div_ceil(BLOCK_SIZE) as u64).to_be_bytes();
         }
-        out.write(&decoded)?;
     }
-    out.flush()?;
-    Ok(())
 }
 
-fn xor(data: &[u8], key: &[u8]) -> Vec<u8> {
-    data.iter().zip(key.iter().cycle()).map(|(a, b)| a ^ b).collect()
+/// Encrypts `input` into `out` with the given mode. `iv` is ignored for ECB.
+/// Without PKCS#7 padding the input of ECB and CBC must be a multiple of the block size.
+/// Parallelizable modes split large chunks of input between `threads`.
+pub fn encrypt<R, W>(input: &mut R, out: &mut W, mode: Mode, key: &[u8], iv: &[u8], pkcs7: bool, threads: usize) -> Result<()>
+where
+    R: Read,
+    W: Write,
+{
+    process_stream(input, out, mode, true, &Keys::new(key), to_iv(mode, iv), pkcs7, threads)
 }
 
-pub fn enc_cbc<R, W>(input: &mut R, key: &[u8], iv: &[u8], out: &mut W) -> Result<()>
+/// Decryption counterpart of `encrypt`.
+pub fn decrypt<R, W>(input: &mut R, out: &mut W, mode: Mode, key: &[u8], iv: &[u8], pkcs7: bool, threads: usize) -> Result<()>
 where
     R: Read,
     W: Write,
 {
-    let keys = Keys::new(key);
-    let mut buf = vec![0u8; BLOCK_SIZE];
-    let mut encoded = iv[..BLOCK_SIZE].to_vec();
-    let mut read_len: usize;
+    process_stream(input, out, mode, false, &Keys::new(key), to_iv(mode, iv), pkcs7, threads)
+}
 
-    loop {
-        read_len = input.read(&mut buf)?;
-        if read_len < BLOCK_SIZE {
-            buf = pad_pkcs7(&buf[..read_len], BLOCK_SIZE);
-        }
-        buf = xor(&buf, &encoded);
-        encoded = enc8(&buf, &keys);
-        out.write(&encoded)?;
-        if read_len < BLOCK_SIZE { break; }
-    }
-    out.flush()?;
-    Ok(())
+pub fn enc_ecb<R, W>(input: &mut R, key: &[u8], out: &mut W) -> Result<()>
+where 
+    R: Read,
+    W: Write,
+{
+    enc_ecb_mt(input, key, out, 1)
+}
+
+/// Same as `enc_ecb`, but splits large chunks of input between `threads`.
+pub fn enc_ecb_mt<R, W>(input: &mut R, key: &[u8], out: &mut W, threads: usize) -> Result<()>
+where 
+    R: Read,
+    W: Write,
+{
+    encrypt(input, out, Mode::Ecb, key, &[], true, threads)
+}
+
+pub fn dec_ecb<R, W>(input: &mut R, key: &[u8], out: &mut W) -> Result<()>
+where
+    R: Read,
+    W: Write,
+{
+    dec_ecb_mt(input, key, out, 1)
+}
+
+/// Same as `dec_ecb`, but splits large chunks of input between `threads`.
+pub fn dec_ecb_mt<R, W>(input: &mut R, key: &[u8], out: &mut W, threads: usize) -> Result<()>
+where
+    R: Read,
+    W: Write,
+{
+    decrypt(input, out, Mode::Ecb, key, &[], true, threads)
+}
+
+pub fn enc_cbc<R, W>(input: &mut R, key: &[u8], iv: &[u8], out: &mut W) -> Result<()>
+where
+    R: Read,
+    W: Write,
+{
+    encrypt(input, out, Mode::Cbc, key, iv, true, 1)
 }
 
 pub fn dec_cbc<R, W>(input: &mut R, key: &[u8], iv: &[u8], out: &mut W) -> Result<()>
@@ -111,23 +523,16 @@ where
     R: Read,
     W: Write,
 {
-    let keys = Keys::new(key);
-    let mut buf = vec![0u8; BLOCK_SIZE];
-    let mut prev_enc = iv[..BLOCK_SIZE].to_vec();
-    let mut read_len = input.read(&mut buf)?;
+    dec_cbc_mt(input, key, iv, out, 1)
+}
 
-    while read_len == BLOCK_SIZE {
-        let mut decoded = dec8(&buf, &keys);
-        decoded = xor(&decoded, &prev_enc);
-        prev_enc.copy_from_slice(&buf);
-        read_len = input.read(&mut buf)?;
-        if read_len < BLOCK_SIZE {
-            decoded = unpad_pkcs7(&decoded, BLOCK_SIZE);
-        }
-        out.write(&decoded)?;
-    }
-    out.flush()?;
-    Ok(())
+/// Same as `dec_cbc`, but splits large chunks of input between `threads`.
+pub fn dec_cbc_mt<R, W>(input: &mut R, key: &[u8], iv: &[u8], out: &mut W, threads: usize) -> Result<()>
+where
+    R: Read,
+    W: Write,
+{
+    decrypt(input, out, Mode::Cbc, key, iv, true, threads)
 }
 
 pub fn enc_cfb<R, W>(input: &mut R, key: &[u8], iv: &[u8], out: &mut W) -> Result<()>
@@ -135,20 +540,7 @@ where
     R: Read,
     W: Write,
 {
-    let keys = Keys::new(key);
-    let mut buf = vec![0u8; BLOCK_SIZE];
-    let mut encoded = iv[..BLOCK_SIZE].to_vec();
-    let mut read_len: usize;
-
-    loop {
-        read_len = input.read(&mut buf)?;
-        encoded = enc8(&encoded, &keys);
-        encoded = xor(&buf[..read_len], &encoded);
-        out.write(&encoded)?;
-        if read_len < BLOCK_SIZE { break; }
-    }
-    out.flush()?;
-    Ok(())
+    encrypt(input, out, Mode::Cfb, key, iv, true, 1)
 }
 
 pub fn dec_cfb<R, W>(input: &mut R, key: &[u8], iv: &[u8], out: &mut W) -> Result<()>
@@ -156,21 +548,43 @@ where
     R: Read,
     W: Write,
 {
-    let keys = Keys::new(key);
-    let mut buf = vec![0u8; BLOCK_SIZE];
-    let mut prev_enc = iv[..BLOCK_SIZE].to_vec();
-    let mut read_len: usize;
+    dec_cfb_mt(input, key, iv, out, 1)
+}
 
-    loop {
-        read_len = input.read(&mut buf)?;
-        let mut decoded = enc8(&prev_enc, &keys);
-        decoded = xor(&buf[..read_len], &decoded);
-        prev_enc.copy_from_slice(&buf);
-        out.write(&decoded)?;
-        if read_len < BLOCK_SIZE { break; }
-    }
-    out.flush()?;
-    Ok(())
+/// Same as `dec_cfb`, but splits large chunks of input between `threads`.
+pub fn dec_cfb_mt<R, W>(input: &mut R, key: &[u8], iv: &[u8], out: &mut W, threads: usize) -> Result<()>
+where
+    R: Read,
+    W: Write,
+{
+    decrypt(input, out, Mode::Cfb, key, iv, true, threads)
+}
+
+/// CTR mode. The iv is used as a big-endian 64 bit counter for the first block.
+/// Encryption and decryption are the same operation.
+pub fn enc_ctr<R, W>(input: &mut R, key: &[u8], iv: &[u8], out: &mut W) -> Result<()>
+where
+    R: Read,
+    W: Write,
+{
+    enc_ctr_mt(input, key, iv, out, 1)
+}
+
+pub fn dec_ctr<R, W>(input: &mut R, key: &[u8], iv: &[u8], out: &mut W) -> Result<()>
+where
+    R: Read,
+    W: Write,
+{
+    enc_ctr_mt(input, key, iv, out, 1)
+}
+
+/// Same as `enc_ctr`, but splits large chunks of input between `threads`.
+pub fn enc_ctr_mt<R, W>(input: &mut R, key: &[u8], iv: &[u8], out: &mut W, threads: usize) -> Result<()>
+where
+    R: Read,
+    W: Write,
+{
+    encrypt(input, out, Mode::Ctr, key, iv, true, threads)
 }
 
 #[allow(non_upper_case_globals)]
@@ -224,4 +638,116 @@ mod tests {
         dec_cfb(&mut crypted.as_slice(), &key, &iv, &mut actual).unwrap();
         assert_eq!(&plain, &actual.as_slice());
     }
+
+    fn test_data(len: usize) -> Vec<u8> {
+        let mut x = 0x12345678u32;
+        (0..len).map(|_| { x = x.wrapping_mul(1664525).wrapping_add(1013904223); (x >> 24) as u8 }).collect()
+    }
+
+    const KEY: [u8; 8] = [0x01, 0x23, 0x45, 0x67, 0x89, 0xAB, 0xCD, 0xEF];
+    const IV: [u8; 8] = [0xFE, 0xDC, 0xBA, 0x98, 0x76, 0x54, 0x32, 0x10];
+    // Around chunk boundaries, both with and without partial blocks
+    const LENGTHS: [usize; 8] = [0, 5, 8, 61, 4096, BLOCK_SIZE * CHUNK_BLOCKS, BLOCK_SIZE * CHUNK_BLOCKS * 3 + 13, BLOCK_SIZE * CHUNK_BLOCKS * 4];
+
+    #[test]
+    fn ecb_multi_block_test() {
+        let keys = Keys::new(&KEY);
+        let plain = test_data(1000 * BLOCK_SIZE);
+        let mut expected = plain.clone();
+        for block in expected.chunks_exact_mut(BLOCK_SIZE) {
+            let (l, r) = encode_block(bytes_to_u32(&block[..4]), bytes_to_u32(&block[4..]), &keys);
+            store_block(block, l, r);
+        }
+        for threads in [1, 3, 8] {
+            let mut actual = plain.clone();
+            enc_ecb_in_place(&mut actual, &keys, threads);
+            assert_eq!(expected, actual);
+            dec_ecb_in_place(&mut actual, &keys, threads);
+            assert_eq!(plain, actual);
+        }
+    }
+
+    #[test]
+    fn ctr_test() {
+        let keys = Keys::new(&KEY);
+        let plain = test_data(29);
+        let mut expected = plain.clone();
+        for (i, block) in expected.chunks_mut(BLOCK_SIZE).enumerate() {
+            let counter = u64::from_be_bytes(IV).wrapping_add(i as u64);
+            let (l, r) = encode_block((counter >> 32) as u32, counter as u32, &keys);
+            xor_block(block, l, r);
+        }
+        let mut actual = Vec::new();
+        enc_ctr(&mut plain.as_slice(), &KEY, &IV, &mut actual).unwrap();
+        assert_eq!(expected, actual);
+
+        let mut decrypted = Vec::new();
+        dec_ctr(&mut actual.as_slice(), &KEY, &IV, &mut decrypted).unwrap();
+        assert_eq!(plain, decrypted);
+    }
+
+    #[test]
+    fn threads_test() {
+        for len in LENGTHS {
+            let plain = test_data(len);
+            let mut single = Vec::new();
+            let mut multi = Vec::new();
+            let mut decrypted = Vec::new();
+
+            enc_ecb(&mut plain.as_slice(), &KEY, &mut single).unwrap();
+            enc_ecb_mt(&mut plain.as_slice(), &KEY, &mut multi, 4).unwrap();
+            assert_eq!(single, multi);
+            dec_ecb_mt(&mut multi.as_slice(), &KEY, &mut decrypted, 4).unwrap();
+            assert_eq!(plain, decrypted);
+
+            single.clear(); multi.clear(); decrypted.clear();
+            enc_ctr(&mut plain.as_slice(), &KEY, &IV, &mut single).unwrap();
+            enc_ctr_mt(&mut plain.as_slice(), &KEY, &IV, &mut multi, 4).unwrap();
+            assert_eq!(single, multi);
+
+            single.clear(); decrypted.clear();
+            enc_cbc(&mut plain.as_slice(), &KEY, &IV, &mut single).unwrap();
+            dec_cbc(&mut single.as_slice(), &KEY, &IV, &mut decrypted).unwrap();
+            assert_eq!(plain, decrypted);
+            decrypted.clear();
+            dec_cbc_mt(&mut single.as_slice(), &KEY, &IV, &mut decrypted, 4).unwrap();
+            assert_eq!(plain, decrypted);
+
+            single.clear(); decrypted.clear();
+            enc_cfb(&mut plain.as_slice(), &KEY, &IV, &mut single).unwrap();
+            dec_cfb(&mut single.as_slice(), &KEY, &IV, &mut decrypted).unwrap();
+            assert_eq!(plain, decrypted);
+            decrypted.clear();
+            dec_cfb_mt(&mut single.as_slice(), &KEY, &IV, &mut decrypted, 4).unwrap();
+            assert_eq!(plain, decrypted);
+        }
+    }
+
+    #[test]
+    fn no_padding_test() {
+        let plain = test_data(64);
+        let mut padded = Vec::new();
+        let mut unpadded = Vec::new();
+        let mut decrypted = Vec::new();
+
+        encrypt(&mut plain.as_slice(), &mut padded, Mode::Cbc, &KEY, &IV, true, 1).unwrap();
+        encrypt(&mut plain.as_slice(), &mut unpadded, Mode::Cbc, &KEY, &IV, false, 1).unwrap();
+        assert_eq!(padded.len(), plain.len() + BLOCK_SIZE);
+        assert_eq!(&padded[..plain.len()], &unpadded[..]);
+
+        decrypt(&mut unpadded.as_slice(), &mut decrypted, Mode::Cbc, &KEY, &IV, false, 1).unwrap();
+        assert_eq!(plain, decrypted);
+
+        let err = encrypt(&mut &plain[..61], &mut Vec::new(), Mode::Ecb, &KEY, &[], false, 1).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn mode_from_str_test() {
+        assert_eq!("CTR".parse(), Ok(Mode::Ctr));
+        assert!("ofb".parse::<Mode>().is_err());
+        for mode in Mode::ALL {
+            assert_eq!(mode.to_string().parse(), Ok(mode));
+        }
+    }
 }
@@ -0,0 +1,120 @@
+//! Asking for secrets on the terminal without echoing them.
+
+use std::io::{self, Result};
+
+/// Prints `prompt` and reads a line from the terminal with echo turned off.
+/// The terminal is used directly, so stdin stays free for the input data.
+#[cfg(unix)]
+pub fn read_secret(prompt: &str) -> Result<String> {
+    use std::fs::OpenOptions;
+    use std::io::{BufRead, BufReader, Write};
+
+    let tty = OpenOptions::new().read(true).write(true).open("/dev/tty")?;
+    write!(&tty, "{}", prompt)?;
+    (&tty).flush()?;
+
+    let mut line = String::new();
+    {
+        let _no_echo = NoEcho::new(&tty)?;
+        BufReader::new(&tty).read_line(&mut line)?;
+    }
+    // The newline typed by the user wasn't echoed either
+    writeln!(&tty)?;
+    Ok(line.trim_end_matches(['\r', '\n']).to_owned())
+}
+
+/// Echo can only be turned off on unix, elsewhere the key would show on the screen
+/// while it's typed, so asking for it is refused.
+#[cfg(not(unix))]
+pub fn read_secret(_prompt: &str) -> Result<String> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "can't turn off echo on this platform, use --key-hex, --key-file or --key-env",
+    ))
+}
+
+/// Terminal with echo turned off, or -1. Signal handlers turn echo back on for it.
+#[cfg(unix)]
+static NO_ECHO_FD: std::sync::atomic::AtomicI32 = std::sync::atomic::AtomicI32::new(-1);
+
+/// Signals that end the process while the user types the key
+#[cfg(unix)]
+const SIGNALS: [libc::c_int; 4] = [libc::SIGINT, libc::SIGTERM, libc::SIGHUP, libc::SIGQUIT];
+
+/// Turns echo back on and ends the process with the default action of `signal`.
+/// `tcgetattr`, `tcsetattr`, `signal` and `raise` are all async-signal-safe.
+#[cfg(unix)]
+extern "C" fn restore_echo(signal: libc::c_int) {
+    let fd = NO_ECHO_FD.load(std::sync::atomic::Ordering::SeqCst);
+    unsafe {
+        let mut term = std::mem::zeroed::<libc::termios>();
+        if fd >= 0 && libc::tcgetattr(fd, &mut term) == 0 {
+            term.c_lflag |= libc::ECHO;
+            libc::tcsetattr(fd, libc::TCSANOW, &term);
+        }
+        libc::signal(signal, libc::SIG_DFL);
+        libc::raise(signal);
+    }
+}
+
+/// Turns terminal echo off for as long as it lives, and back on when
+/// it is dropped or a signal ends the process.
+#[cfg(unix)]
+struct NoEcho<'a> {
+    tty: &'a std::fs::File,
+    saved: libc::termios,
+    old_actions: Vec<(libc::c_int, libc::sigaction)>,
+}
+
+#[cfg(unix)]
+impl<'a> NoEcho<'a> {
+    fn new(tty: &'a std::fs::File) -> Result<Self> {
+        use std::os::fd::AsRawFd;
+
+        let fd = tty.as_raw_fd();
+        let mut saved = unsafe { std::mem::zeroed::<libc::termios>() };
+        if unsafe { libc::tcgetattr(fd, &mut saved) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        // From here on dropping the guard puts back the settings and signal handlers
+        let mut guard = NoEcho { tty, saved, old_actions: Vec::new() };
+        NO_ECHO_FD.store(fd, std::sync::atomic::Ordering::SeqCst);
+        for signal in SIGNALS {
+            unsafe {
+                let mut action = std::mem::zeroed::<libc::sigaction>();
+                action.sa_sigaction = restore_echo as extern "C" fn(libc::c_int) as libc::sighandler_t;
+                libc::sigemptyset(&mut action.sa_mask);
+                let mut old = std::mem::zeroed::<libc::sigaction>();
+                if libc::sigaction(signal, &action, &mut old) != 0 { continue; }
+                if old.sa_sigaction == libc::SIG_IGN {
+                    // Ignored signals don't end the process, keep it that way
+                    libc::sigaction(signal, &old, std::ptr::null_mut());
+                } else {
+                    guard.old_actions.push((signal, old));
+                }
+            }
+        }
+
+        let mut term = saved;
+        term.c_lflag &= !libc::ECHO;
+        if unsafe { libc::tcsetattr(fd, libc::TCSAFLUSH, &term) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(guard)
+    }
+}
+
+#[cfg(unix)]
+impl Drop for NoEcho<'_> {
+    fn drop(&mut self) {
+        use std::os::fd::AsRawFd;
+
+        unsafe {
+            libc::tcsetattr(self.tty.as_raw_fd(), libc::TCSANOW, &self.saved);
+            for (signal, old) in &self.old_actions {
+                libc::sigaction(*signal, old, std::ptr::null_mut());
+            }
+        }
+        NO_ECHO_FD.store(-1, std::sync::atomic::Ordering::SeqCst);
+    }
+}